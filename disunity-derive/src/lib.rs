@@ -245,16 +245,13 @@ enum GetAttrError {
 }
 
 fn get_disunity_attr(attrs: Vec<Attribute>) -> Result<Attribute, GetAttrError> {
-    let mut attributes = attrs
-        .into_iter()
-        .filter(|attribute| {
-            attribute
-                .path
-                .get_ident()
-                .map(|ident| ident == "disunity")
-                .unwrap_or(false)
-        })
-        .map(|attribute| attribute);
+    let mut attributes = attrs.into_iter().filter(|attribute| {
+        attribute
+            .path
+            .get_ident()
+            .map(|ident| ident == "disunity")
+            .unwrap_or(false)
+    });
 
     match (attributes.next(), attributes.next()) {
         (Some(attribute), None) => Ok(attribute),
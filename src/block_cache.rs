@@ -0,0 +1,73 @@
+use std::{collections::VecDeque, sync::Arc};
+
+/// Keeps the most recently used decompressed blocks of a bundle around, so that reading objects
+/// one after the other out of the same block doesn't decompress it every time. Bundles only
+/// cache a handful of blocks, which a linear search goes through faster than a map
+#[derive(Debug)]
+pub(crate) struct BlockCache {
+    capacity: usize,
+    /// Least recently used first
+    blocks: VecDeque<(usize, Arc<Vec<u8>>)>,
+}
+
+impl BlockCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        BlockCache {
+            capacity,
+            blocks: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub(crate) fn get(&mut self, index: usize) -> Option<Arc<Vec<u8>>> {
+        let position = self
+            .blocks
+            .iter()
+            .position(|(cached, _)| *cached == index)?;
+        let entry = self.blocks.remove(position)?;
+        let block = entry.1.clone();
+        self.blocks.push_back(entry);
+
+        Some(block)
+    }
+
+    pub(crate) fn insert(&mut self, index: usize, block: Arc<Vec<u8>>) {
+        self.blocks.retain(|(cached, _)| *cached != index);
+        self.blocks.push_back((index, block));
+        self.shrink();
+    }
+
+    pub(crate) fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.shrink();
+    }
+
+    fn shrink(&mut self) {
+        while self.blocks.len() > self.capacity {
+            self.blocks.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BlockCache;
+    use std::sync::Arc;
+
+    #[test]
+    fn evict_least_recently_used() {
+        let mut cache = BlockCache::new(2);
+        cache.insert(0, Arc::new(vec![0]));
+        cache.insert(1, Arc::new(vec![1]));
+        // Using block 0 makes block 1 the next to go
+        assert_eq!(*cache.get(0).unwrap(), [0]);
+        cache.insert(2, Arc::new(vec![2]));
+        assert!(cache.get(1).is_none());
+        assert_eq!(*cache.get(0).unwrap(), [0]);
+        assert_eq!(*cache.get(2).unwrap(), [2]);
+
+        cache.set_capacity(0);
+        assert!(cache.get(0).is_none());
+        cache.insert(3, Arc::new(vec![3]));
+        assert!(cache.get(3).is_none());
+    }
+}
@@ -0,0 +1,1072 @@
+use crate::{
+    block_cache::BlockCache,
+    compression::{decompress, decompress_lzma_stream, Compression},
+    detect::detect,
+    error::{string_error_to_parse_error, ParserContext},
+    utils::{BufReadExt, ReadExt, SeekExt},
+    BundleSignature, Endianess, FileKind, ParseError, ParseResult, SerializedFile,
+};
+use std::{
+    borrow::Cow,
+    fs::File,
+    io::{BufRead, BufReader, Cursor, Read, Seek, SeekFrom},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+/// Flags of a UnityFS bundle, stored in its header
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BundleFlags(pub u32);
+
+impl BundleFlags {
+    pub(crate) const BLOCKS_AND_DIRECTORY_INFO_COMBINED: u32 = 0x40;
+    const BLOCKS_INFO_AT_THE_END: u32 = 0x80;
+    const OLD_WEB_PLUGIN_COMPATIBILITY: u32 = 0x100;
+    const BLOCK_INFO_NEEDS_PADDING_AT_START: u32 = 0x200;
+
+    /// Compression of the blocks info
+    pub fn compression(&self) -> Compression {
+        Compression::from_flags(self.0)
+    }
+
+    /// Set by every bundle since the blocks and the directory started sharing one section
+    pub fn blocks_and_directory_info_combined(&self) -> bool {
+        self.0 & Self::BLOCKS_AND_DIRECTORY_INFO_COMBINED != 0
+    }
+
+    /// The blocks info is at the end of the bundle instead of right after the header, which
+    /// lets bundles be written in one pass
+    pub fn blocks_info_at_the_end(&self) -> bool {
+        self.0 & Self::BLOCKS_INFO_AT_THE_END != 0
+    }
+
+    pub fn old_web_plugin_compatibility(&self) -> bool {
+        self.0 & Self::OLD_WEB_PLUGIN_COMPATIBILITY != 0
+    }
+
+    /// The blocks start at a multiple of 16 bytes after the blocks info
+    pub fn block_info_needs_padding_at_start(&self) -> bool {
+        self.0 & Self::BLOCK_INFO_NEEDS_PADDING_AT_START != 0
+    }
+}
+
+/// Header of a bundle. UnityWeb and UnityRaw bundles before version 6 have no blocks info, their
+/// blocks info sizes are 0 and their flags only hold the compression of their data
+#[derive(Debug)]
+pub struct BundleHeader {
+    pub signature: BundleSignature,
+    pub version: u32,
+    /// Version of the web player the bundle targets, "5.x.x" for every UnityFS bundle
+    pub player_version: String,
+    /// Unity version that built the bundle, like "2021.3.5f1"
+    pub engine_version: String,
+    pub size: u64,
+    pub compressed_blocks_info_size: u32,
+    pub uncompressed_blocks_info_size: u32,
+    pub flags: BundleFlags,
+}
+
+/// How much of a UnityWeb or UnityRaw bundle before version 6 has to be downloaded for one more
+/// of its levels to be loadable, both sizes count from the start of the data and include every
+/// level before
+#[derive(Clone, Copy, Debug)]
+pub struct LevelRange {
+    pub compressed_end: u32,
+    pub uncompressed_end: u32,
+}
+
+/// One of the blocks the data of a bundle's nodes is split into and compressed by
+#[derive(Clone, Copy, Debug)]
+pub struct StorageBlock {
+    pub uncompressed_size: u32,
+    pub compressed_size: u32,
+    pub flags: u16,
+}
+
+impl StorageBlock {
+    pub fn compression(&self) -> Compression {
+        Compression::from_flags(self.flags.into())
+    }
+}
+
+/// A file stored inside of a bundle, its data is a range of the bundle's blocks once they are
+/// decompressed and put back to back
+#[derive(Clone, Debug)]
+pub struct BundleNode {
+    pub offset: u64,
+    pub size: u64,
+    pub flags: u32,
+    /// Name of the file, like "CAB-0123456789abcdef0123456789abcdef" for a serialized file or
+    /// "CAB-0123456789abcdef0123456789abcdef.resS" for its resource stream
+    pub path: String,
+}
+
+impl BundleNode {
+    const SERIALIZED_FILE_FLAG: u32 = 0x4;
+
+    pub fn is_serialized_file(&self) -> bool {
+        self.flags & Self::SERIALIZED_FILE_FLAG != 0
+    }
+}
+
+/// Where the blocks of a bundle are read from when they're needed
+#[derive(Debug)]
+enum BlockSource {
+    /// UnityWeb and UnityRaw bundles before version 6 are a single stream that has to be
+    /// decompressed as a whole to find their nodes
+    Decompressed(Arc<Vec<u8>>),
+    /// The compressed blocks back to back
+    Memory(Vec<u8>),
+    /// The compressed blocks are in `file` starting at `start`
+    File { file: Mutex<File>, start: u64 },
+}
+
+/// Where a block starts among the compressed blocks and in the data once decompressed
+#[derive(Clone, Copy, Debug)]
+struct BlockOffset {
+    compressed: u64,
+    uncompressed: u64,
+}
+
+/// A parsed asset bundle, such as a `.bundle` file or the `data.unity3d` of a game. Its blocks
+/// are only decompressed once a node overlapping them is read, and the last few of them are kept
+/// around for the next reads
+#[derive(Debug)]
+pub struct Bundle {
+    pub header: BundleHeader,
+    /// Only UnityWeb and UnityRaw bundles before version 6 are split into levels
+    pub levels: Vec<LevelRange>,
+    pub blocks: Vec<StorageBlock>,
+    pub nodes: Vec<BundleNode>,
+    source: BlockSource,
+    block_offsets: Vec<BlockOffset>,
+    /// Size of all blocks once decompressed
+    data_size: u64,
+    cache: Mutex<BlockCache>,
+}
+
+fn parse_string<R: BufRead>(file: &mut R, expected: &str) -> ParseResult<String> {
+    file.read_null_terminated_string()
+        .map_err(string_error_to_parse_error(expected))
+}
+
+/// Reads the signature and format version every kind of bundle starts with
+fn parse_signature<R: BufRead>(file: &mut R) -> ParseResult<(BundleSignature, u32)> {
+    let signature = parse_string(file, "bundle signature")?;
+    let signature = match signature.as_str() {
+        "UnityFS" => BundleSignature::UnityFS,
+        "UnityWeb" => BundleSignature::UnityWeb,
+        "UnityRaw" => BundleSignature::UnityRaw,
+        _ => {
+            return Err(ParseError::expected(
+                "a UnityFS, UnityWeb or UnityRaw bundle signature",
+                signature.into_bytes(),
+                None,
+            ))
+        }
+    };
+    let version = file
+        .read_u32(Endianess::Big)
+        .context("reading bundle version")?;
+
+    Ok((signature, version))
+}
+
+/// UnityWeb and UnityRaw bundles of version 6, written by the last versions of Unity 5, are laid
+/// out like UnityFS bundles
+fn is_legacy_layout(signature: BundleSignature, version: u32) -> bool {
+    signature != BundleSignature::UnityFS && version < 6
+}
+
+fn parse_bundle_header<R: BufRead + Seek>(
+    file: &mut R,
+    signature: BundleSignature,
+    version: u32,
+) -> ParseResult<BundleHeader> {
+    let player_version = parse_string(file, "bundle player version")?;
+    let engine_version = parse_string(file, "bundle engine version")?;
+    let size = file
+        .read_u64(Endianess::Big)
+        .context("reading bundle size")?;
+    let compressed_blocks_info_size = file
+        .read_u32(Endianess::Big)
+        .context("reading bundle compressed blocks info size")?;
+    let uncompressed_blocks_info_size = file
+        .read_u32(Endianess::Big)
+        .context("reading bundle uncompressed blocks info size")?;
+    let flags = file
+        .read_u32(Endianess::Big)
+        .context("reading bundle flags")?;
+
+    Ok(BundleHeader {
+        signature,
+        version,
+        player_version,
+        engine_version,
+        size,
+        compressed_blocks_info_size,
+        uncompressed_blocks_info_size,
+        flags: BundleFlags(flags),
+    })
+}
+
+fn parse_blocks_info(blocks_info: &[u8]) -> ParseResult<(Vec<StorageBlock>, Vec<BundleNode>)> {
+    let mut blocks_info = Cursor::new(blocks_info);
+
+    // Hash of the uncompressed data, Unity doesn't fill it in
+    blocks_info
+        .skip(16)
+        .context("ignoring blocks info data hash")?;
+
+    let blocks_count = blocks_info
+        .read_u32(Endianess::Big)
+        .context("reading blocks count")?;
+    let blocks = (0..blocks_count)
+        .map(|_| {
+            let uncompressed_size = blocks_info
+                .read_u32(Endianess::Big)
+                .context("reading block uncompressed size")?;
+            let compressed_size = blocks_info
+                .read_u32(Endianess::Big)
+                .context("reading block compressed size")?;
+            let flags = blocks_info
+                .read_u16(Endianess::Big)
+                .context("reading block flags")?;
+
+            Ok(StorageBlock {
+                uncompressed_size,
+                compressed_size,
+                flags,
+            })
+        })
+        .collect::<ParseResult<Vec<_>>>()?;
+
+    let nodes_count = blocks_info
+        .read_u32(Endianess::Big)
+        .context("reading nodes count")?;
+    let nodes = (0..nodes_count)
+        .map(|_| {
+            let offset = blocks_info
+                .read_u64(Endianess::Big)
+                .context("reading node offset")?;
+            let size = blocks_info
+                .read_u64(Endianess::Big)
+                .context("reading node size")?;
+            let flags = blocks_info
+                .read_u32(Endianess::Big)
+                .context("reading node flags")?;
+            let path = parse_string(&mut blocks_info, "node path")?;
+
+            Ok(BundleNode {
+                offset,
+                size,
+                flags,
+                path,
+            })
+        })
+        .collect::<ParseResult<Vec<_>>>()?;
+
+    Ok((blocks, nodes))
+}
+
+/// Parses a UnityWeb or UnityRaw bundle, whose nodes are listed at the start of their data and
+/// whose data is a single LZMA stream for UnityWeb and stored as is for UnityRaw
+fn parse_legacy_bundle<R: BufRead + Seek>(
+    file: &mut R,
+    signature: BundleSignature,
+    version: u32,
+) -> ParseResult<Bundle> {
+    let player_version = parse_string(file, "bundle player version")?;
+    let engine_version = parse_string(file, "bundle engine version")?;
+    if version >= 4 {
+        // Hash and CRC of the data, nothing checks them
+        file.skip(16 + 4).context("ignoring bundle hash and CRC")?;
+    }
+    let _minimum_streamed_bytes = file
+        .read_u32(Endianess::Big)
+        .context("reading bundle minimum streamed bytes")?;
+    let header_size = file
+        .read_u32(Endianess::Big)
+        .context("reading bundle header size")?;
+    let _levels_before_streaming = file
+        .read_u32(Endianess::Big)
+        .context("reading bundle number of levels to download before streaming")?;
+    let levels_count = file
+        .read_u32(Endianess::Big)
+        .context("reading bundle levels count")?;
+    let levels = (0..levels_count)
+        .map(|_| {
+            let compressed_end = file
+                .read_u32(Endianess::Big)
+                .context("reading level compressed end")?;
+            let uncompressed_end = file
+                .read_u32(Endianess::Big)
+                .context("reading level uncompressed end")?;
+
+            Ok(LevelRange {
+                compressed_end,
+                uncompressed_end,
+            })
+        })
+        .collect::<ParseResult<Vec<_>>>()?;
+    let complete_file_size = if version >= 2 {
+        Some(
+            file.read_u32(Endianess::Big)
+                .context("reading bundle complete file size")?,
+        )
+    } else {
+        None
+    };
+
+    // Every level includes the ones before it, so the last one covers the whole data
+    let last_level = *levels.last().ok_or_else(|| {
+        ParseError::expected("a bundle with at least one level", Vec::new(), None)
+    })?;
+    let compression = match signature {
+        BundleSignature::UnityWeb => Compression::Lzma,
+        _ => Compression::None,
+    };
+    let block = StorageBlock {
+        uncompressed_size: last_level.uncompressed_end,
+        compressed_size: match compression {
+            Compression::None => last_level.uncompressed_end,
+            _ => last_level.compressed_end,
+        },
+        flags: compression.id() as u16,
+    };
+
+    file.seek(SeekFrom::Start(header_size.into()))
+        .context("seeking to the end of the bundle header")?;
+    let mut compressed = Vec::new();
+    file.take(block.compressed_size.into())
+        .read_to_end(&mut compressed)
+        .context("reading bundle data")?;
+    let data = match compression {
+        Compression::Lzma => decompress_lzma_stream(&compressed, block.uncompressed_size as usize)?,
+        _ => decompress(compression, &compressed, block.uncompressed_size as usize)?,
+    };
+    let nodes = parse_legacy_nodes(&data)?;
+
+    let header = BundleHeader {
+        signature,
+        version,
+        player_version,
+        engine_version,
+        size: complete_file_size.map_or(
+            u64::from(header_size) + u64::from(block.compressed_size),
+            u64::from,
+        ),
+        compressed_blocks_info_size: 0,
+        uncompressed_blocks_info_size: 0,
+        flags: BundleFlags(compression.id()),
+    };
+
+    Ok(Bundle::new(
+        header,
+        levels,
+        vec![block],
+        nodes,
+        BlockSource::Decompressed(Arc::new(data)),
+    ))
+}
+
+/// Legacy bundles have no node flags, serialized files are told apart from other nodes by their
+/// content instead
+fn parse_legacy_nodes(data: &[u8]) -> ParseResult<Vec<BundleNode>> {
+    let mut directory = Cursor::new(data);
+    let nodes_count = directory
+        .read_u32(Endianess::Big)
+        .context("reading nodes count")?;
+
+    (0..nodes_count)
+        .map(|_| {
+            let path = parse_string(&mut directory, "node path")?;
+            let offset = directory
+                .read_u32(Endianess::Big)
+                .context("reading node offset")?;
+            let size = directory
+                .read_u32(Endianess::Big)
+                .context("reading node size")?;
+
+            let node_data = data
+                .get(offset as usize..)
+                .and_then(|rest| rest.get(..size as usize));
+            let flags = match node_data.map(|bytes| detect(&mut Cursor::new(bytes))) {
+                Some(Ok(FileKind::SerializedFile { .. })) => BundleNode::SERIALIZED_FILE_FLAG,
+                _ => 0,
+            };
+
+            Ok(BundleNode {
+                offset: offset.into(),
+                size: size.into(),
+                flags,
+                path,
+            })
+        })
+        .collect()
+}
+
+/// Parses everything of a UnityFS bundle but its blocks, leaving `file` at the start of its blocks
+fn parse_unity_fs<R: BufRead + Seek>(
+    file: &mut R,
+    signature: BundleSignature,
+    version: u32,
+) -> ParseResult<(BundleHeader, Vec<StorageBlock>, Vec<BundleNode>)> {
+    let header = parse_bundle_header(file, signature, version)?;
+    if header.version >= 7 {
+        file.align_16().context("aligning bundle header")?;
+    }
+
+    let compressed_blocks_info_size = header.compressed_blocks_info_size.into();
+    let mut compressed_blocks_info = Vec::new();
+    if header.flags.blocks_info_at_the_end() {
+        let header_end = file
+            .stream_position()
+            .context("reading bundle header end")?;
+        let end = file
+            .seek(SeekFrom::End(0))
+            .context("seeking to the end of the bundle")?;
+        let blocks_info_start = end
+            .checked_sub(compressed_blocks_info_size)
+            .ok_or_else(|| {
+                ParseError::expected(
+                    "a blocks info smaller than the bundle",
+                    Vec::from(header.compressed_blocks_info_size.to_le_bytes()),
+                    None,
+                )
+            })?;
+        file.seek(SeekFrom::Start(blocks_info_start))
+            .context("seeking to the blocks info at the end of the bundle")?;
+        file.take(compressed_blocks_info_size)
+            .read_to_end(&mut compressed_blocks_info)
+            .context("reading blocks info")?;
+        file.seek(SeekFrom::Start(header_end))
+            .context("seeking back to the end of the bundle header")?;
+    } else {
+        file.take(compressed_blocks_info_size)
+            .read_to_end(&mut compressed_blocks_info)
+            .context("reading blocks info")?;
+    }
+
+    let blocks_info = decompress(
+        header.flags.compression(),
+        &compressed_blocks_info,
+        header.uncompressed_blocks_info_size as usize,
+    )?;
+    let (blocks, nodes) = parse_blocks_info(&blocks_info)?;
+
+    if header.flags.block_info_needs_padding_at_start() {
+        file.align_16().context("aligning bundle blocks")?;
+    }
+
+    Ok((header, blocks, nodes))
+}
+
+fn compressed_blocks_size(blocks: &[StorageBlock]) -> u64 {
+    blocks
+        .iter()
+        .map(|block| u64::from(block.compressed_size))
+        .sum()
+}
+
+fn check_blocks_fit(blocks: &[StorageBlock], available: u64) -> ParseResult<()> {
+    let size = compressed_blocks_size(blocks);
+    if size > available {
+        return Err(ParseError::expected(
+            format!("{size} bytes of blocks to fit in the {available} bytes left in the bundle"),
+            Vec::new(),
+            None,
+        ));
+    }
+
+    Ok(())
+}
+
+impl Bundle {
+    /// How many decompressed blocks are kept around until `set_cached_blocks` is called
+    pub const DEFAULT_CACHED_BLOCKS: usize = 8;
+
+    fn new(
+        header: BundleHeader,
+        levels: Vec<LevelRange>,
+        blocks: Vec<StorageBlock>,
+        nodes: Vec<BundleNode>,
+        source: BlockSource,
+    ) -> Self {
+        let mut offset = BlockOffset {
+            compressed: 0,
+            uncompressed: 0,
+        };
+        let block_offsets = blocks
+            .iter()
+            .map(|block| {
+                let start = offset;
+                offset.compressed += u64::from(block.compressed_size);
+                offset.uncompressed += u64::from(block.uncompressed_size);
+                start
+            })
+            .collect();
+
+        Bundle {
+            header,
+            levels,
+            blocks,
+            nodes,
+            source,
+            block_offsets,
+            data_size: offset.uncompressed,
+            cache: Mutex::new(BlockCache::new(Self::DEFAULT_CACHED_BLOCKS)),
+        }
+    }
+
+    /// Opens and parses the bundle at `path`. The file stays open and blocks are read out of it
+    /// when they're needed
+    pub fn open<P: AsRef<Path>>(path: P) -> ParseResult<Self> {
+        let file = File::open(path).context("opening bundle")?;
+        let mut reader = BufReader::new(file);
+
+        let (signature, version) = parse_signature(&mut reader)?;
+        if is_legacy_layout(signature, version) {
+            return parse_legacy_bundle(&mut reader, signature, version);
+        }
+
+        let (header, blocks, nodes) = parse_unity_fs(&mut reader, signature, version)?;
+        let start = reader
+            .stream_position()
+            .context("reading bundle blocks start")?;
+        let len = reader
+            .seek(SeekFrom::End(0))
+            .context("seeking to the end of the bundle")?;
+        check_blocks_fit(&blocks, len.saturating_sub(start))?;
+
+        let source = BlockSource::File {
+            file: Mutex::new(reader.into_inner()),
+            start,
+        };
+        Ok(Self::new(header, Vec::new(), blocks, nodes, source))
+    }
+
+    /// Parses a bundle held in memory
+    pub fn from_bytes(bytes: &[u8]) -> ParseResult<Self> {
+        Self::parse(&mut Cursor::new(bytes))
+    }
+
+    /// Parses a bundle from a buffered seekable reader positioned at the start of the bundle. The
+    /// reader isn't kept so the compressed blocks are read into memory, `open` reads them from the
+    /// file instead
+    pub fn parse<R: BufRead + Seek>(file: &mut R) -> ParseResult<Self> {
+        let (signature, version) = parse_signature(file)?;
+        if is_legacy_layout(signature, version) {
+            return parse_legacy_bundle(file, signature, version);
+        }
+
+        let (header, blocks, nodes) = parse_unity_fs(file, signature, version)?;
+        let mut compressed = Vec::new();
+        file.take(compressed_blocks_size(&blocks))
+            .read_to_end(&mut compressed)
+            .context("reading bundle blocks")?;
+        check_blocks_fit(&blocks, compressed.len() as u64)?;
+
+        Ok(Self::new(
+            header,
+            Vec::new(),
+            blocks,
+            nodes,
+            BlockSource::Memory(compressed),
+        ))
+    }
+
+    /// Changes how many decompressed blocks are kept around for the next reads, 0 decompresses
+    /// blocks every time they're read
+    pub fn set_cached_blocks(&self, count: usize) {
+        self.cache.lock().unwrap().set_capacity(count);
+    }
+
+    /// Looks up a node by its path
+    pub fn node(&self, path: &str) -> Option<&BundleNode> {
+        self.nodes.iter().find(|node| node.path == path)
+    }
+
+    /// Reads the data of one of this bundle's nodes
+    pub fn read_node(&self, node: &BundleNode) -> ParseResult<Vec<u8>> {
+        self.read_node_range(node, 0, node.size)
+    }
+
+    /// Reads `len` bytes starting `offset` bytes into one of this bundle's nodes, only
+    /// decompressing the blocks they overlap
+    pub fn read_node_range(
+        &self,
+        node: &BundleNode,
+        offset: u64,
+        len: u64,
+    ) -> ParseResult<Vec<u8>> {
+        let node_fits = node
+            .offset
+            .checked_add(node.size)
+            .is_some_and(|end| end <= self.data_size);
+        if !node_fits {
+            return Err(ParseError::expected(
+                format!(
+                    "node {} at {} with {} bytes to fit in the {} bytes of the bundle's blocks",
+                    node.path, node.offset, node.size, self.data_size
+                ),
+                Vec::new(),
+                None,
+            ));
+        }
+        let range_fits = offset.checked_add(len).is_some_and(|end| end <= node.size);
+        if !range_fits {
+            return Err(ParseError::expected(
+                format!(
+                    "{len} bytes at {offset} to fit in the {} bytes of node {}",
+                    node.size, node.path
+                ),
+                Vec::new(),
+                None,
+            ));
+        }
+
+        self.read_data(node.offset + offset, len)
+    }
+
+    /// Reads a range of the data that was checked to be within the blocks
+    fn read_data(&self, start: u64, len: u64) -> ParseResult<Vec<u8>> {
+        let mut data = Vec::with_capacity(len as usize);
+        if len == 0 {
+            return Ok(data);
+        }
+
+        let mut index = self
+            .block_offsets
+            .partition_point(|offset| offset.uncompressed <= start)
+            - 1;
+        while (data.len() as u64) < len {
+            let block_start = self.block_offsets[index].uncompressed;
+            let block = self.block(index)?;
+            let from = (start + data.len() as u64 - block_start) as usize;
+            let to = (start + len - block_start).min(block.len() as u64) as usize;
+            data.extend_from_slice(&block[from..to]);
+            index += 1;
+        }
+
+        Ok(data)
+    }
+
+    /// Decompresses a block, or takes it from the cache if it was decompressed recently
+    fn block(&self, index: usize) -> ParseResult<Arc<Vec<u8>>> {
+        if let Some(block) = self.cache.lock().unwrap().get(index) {
+            return Ok(block);
+        }
+
+        let block = self.blocks[index];
+        let offset = self.block_offsets[index].compressed;
+        let compressed = match &self.source {
+            BlockSource::Decompressed(data) => return Ok(data.clone()),
+            BlockSource::Memory(bytes) => Cow::Borrowed(
+                &bytes[offset as usize..offset as usize + block.compressed_size as usize],
+            ),
+            BlockSource::File { file, start } => {
+                let mut file = file.lock().unwrap();
+                file.seek(SeekFrom::Start(start + offset))
+                    .context("seeking to bundle block")?;
+                let mut compressed = vec![0; block.compressed_size as usize];
+                file.read_exact(&mut compressed)
+                    .context("reading bundle block")?;
+                Cow::Owned(compressed)
+            }
+        };
+        let decompressed = Arc::new(decompress(
+            block.compression(),
+            &compressed,
+            block.uncompressed_size as usize,
+        )?);
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(index, decompressed.clone());
+
+        Ok(decompressed)
+    }
+
+    /// Parses one of this bundle's nodes as a serialized file
+    pub fn read_serialized_file(&self, node: &BundleNode) -> ParseResult<SerializedFile> {
+        SerializedFile::from_bytes(&self.read_node(node)?)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::{Bundle, BundleFlags};
+    use crate::{
+        compression::{compress, tests::compress_lzma_stream},
+        tests::version_22_file,
+        BundleSignature, BundleWriter, Compression, NodeData,
+    };
+    use std::{env, fs, process};
+
+    /// Builds an uncompressed UnityFS bundle holding `nodes` in a single block
+    pub(crate) fn uncompressed_bundle(
+        version: u32,
+        flags: u32,
+        nodes: &[(&str, u32, &[u8])],
+    ) -> Vec<u8> {
+        compressed_bundle(version, flags, Compression::None, nodes)
+    }
+
+    /// Builds a UnityFS bundle holding `nodes` in a single block, with both that block and the
+    /// blocks info compressed with `compression`
+    pub(crate) fn compressed_bundle(
+        version: u32,
+        flags: u32,
+        compression: Compression,
+        nodes: &[(&str, u32, &[u8])],
+    ) -> Vec<u8> {
+        let compression_id = compression.id();
+        // LZ4HC data is plain LZ4 that took more effort to find, readers can't tell the difference
+        let compression = match compression {
+            Compression::Lz4HC => Compression::Lz4,
+            compression => compression,
+        };
+        let mut data = Vec::new();
+        for (_, _, node) in nodes {
+            data.extend_from_slice(node);
+        }
+        let compressed_data = compress(compression, &data).unwrap();
+
+        let mut blocks_info = vec![0; 16];
+        blocks_info.extend_from_slice(&1u32.to_be_bytes());
+        blocks_info.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        blocks_info.extend_from_slice(&(compressed_data.len() as u32).to_be_bytes());
+        blocks_info.extend_from_slice(&(compression_id as u16).to_be_bytes());
+        blocks_info.extend_from_slice(&(nodes.len() as u32).to_be_bytes());
+        let mut offset = 0;
+        for (path, node_flags, node) in nodes {
+            blocks_info.extend_from_slice(&(offset as u64).to_be_bytes());
+            blocks_info.extend_from_slice(&(node.len() as u64).to_be_bytes());
+            blocks_info.extend_from_slice(&node_flags.to_be_bytes());
+            blocks_info.extend_from_slice(path.as_bytes());
+            blocks_info.push(0);
+            offset += node.len();
+        }
+        let compressed_blocks_info = compress(compression, &blocks_info).unwrap();
+
+        let mut bundle = b"UnityFS\0".to_vec();
+        bundle.extend_from_slice(&version.to_be_bytes());
+        bundle.extend_from_slice(b"5.x.x\0");
+        bundle.extend_from_slice(b"2021.3.5f1\0");
+        let size_offset = bundle.len();
+        bundle.extend_from_slice(&0u64.to_be_bytes());
+        bundle.extend_from_slice(&(compressed_blocks_info.len() as u32).to_be_bytes());
+        bundle.extend_from_slice(&(blocks_info.len() as u32).to_be_bytes());
+        bundle.extend_from_slice(&(flags | 0x40 | compression_id).to_be_bytes());
+        if version >= 7 {
+            bundle.resize(bundle.len().next_multiple_of(16), 0);
+        }
+
+        if BundleFlags(flags).blocks_info_at_the_end() {
+            bundle.extend_from_slice(&compressed_data);
+            bundle.extend_from_slice(&compressed_blocks_info);
+        } else {
+            bundle.extend_from_slice(&compressed_blocks_info);
+            if BundleFlags(flags).block_info_needs_padding_at_start() {
+                bundle.resize(bundle.len().next_multiple_of(16), 0);
+            }
+            bundle.extend_from_slice(&compressed_data);
+        }
+
+        let size = bundle.len() as u64;
+        bundle[size_offset..size_offset + 8].copy_from_slice(&size.to_be_bytes());
+        bundle
+    }
+
+    /// Builds a UnityWeb or UnityRaw bundle holding `nodes` in a single level
+    fn legacy_bundle(signature: &str, version: u32, nodes: &[(&str, &[u8])]) -> Vec<u8> {
+        let directory_len: usize = 4 + nodes
+            .iter()
+            .map(|(path, _)| path.len() + 1 + 8)
+            .sum::<usize>();
+        let mut data = Vec::from((nodes.len() as u32).to_be_bytes());
+        let mut offset = directory_len;
+        for (path, node) in nodes {
+            data.extend_from_slice(path.as_bytes());
+            data.push(0);
+            data.extend_from_slice(&(offset as u32).to_be_bytes());
+            data.extend_from_slice(&(node.len() as u32).to_be_bytes());
+            offset += node.len();
+        }
+        for (_, node) in nodes {
+            data.extend_from_slice(node);
+        }
+        let compressed = match signature {
+            "UnityWeb" => compress_lzma_stream(&data),
+            _ => data.clone(),
+        };
+
+        let mut header = format!("{signature}\0").into_bytes();
+        header.extend_from_slice(&version.to_be_bytes());
+        header.extend_from_slice(b"3.x.x\x003.5.7f6\0");
+        if version >= 4 {
+            header.extend_from_slice(&[0; 20]);
+        }
+        header.extend_from_slice(&(compressed.len() as u32).to_be_bytes());
+        let header_size_offset = header.len();
+        header.extend_from_slice(&0u32.to_be_bytes());
+        header.extend_from_slice(&1u32.to_be_bytes());
+        header.extend_from_slice(&1u32.to_be_bytes());
+        header.extend_from_slice(&(compressed.len() as u32).to_be_bytes());
+        header.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        let complete_size_offset = header.len();
+        if version >= 2 {
+            header.extend_from_slice(&0u32.to_be_bytes());
+        }
+        if version >= 3 {
+            header.extend_from_slice(&(directory_len as u32).to_be_bytes());
+        }
+        // Unity pads the header, which is why its size is stored
+        header.resize(header.len().next_multiple_of(4) + 4, 0);
+
+        let header_size = header.len() as u32;
+        header[header_size_offset..header_size_offset + 4]
+            .copy_from_slice(&header_size.to_be_bytes());
+        let complete_size = header_size + compressed.len() as u32;
+        if version >= 2 {
+            header[complete_size_offset..complete_size_offset + 4]
+                .copy_from_slice(&complete_size.to_be_bytes());
+        }
+        header.extend_from_slice(&compressed);
+        header
+    }
+
+    #[test]
+    fn parse_legacy_bundles() {
+        let serialized_file = version_22_file();
+        let nodes: &[(&str, &[u8])] =
+            &[("CAB-0123", &serialized_file), ("CAB-0123.resS", b"pixels")];
+
+        for (signature, compression) in [
+            (BundleSignature::UnityWeb, Compression::Lzma),
+            (BundleSignature::UnityRaw, Compression::None),
+        ] {
+            for version in [1, 3, 4] {
+                let bytes = legacy_bundle(signature.as_str(), version, nodes);
+                let bundle = Bundle::from_bytes(&bytes).unwrap();
+                assert_eq!(bundle.header.signature, signature);
+                assert_eq!(bundle.header.version, version);
+                assert_eq!(bundle.header.player_version, "3.x.x");
+                assert_eq!(bundle.header.engine_version, "3.5.7f6");
+                assert_eq!(bundle.header.size, bytes.len() as u64);
+                assert_eq!(bundle.header.flags.compression(), compression);
+                assert_eq!(bundle.levels.len(), 1);
+                assert_eq!(bundle.blocks[0].compression(), compression);
+                assert_eq!(bundle.nodes.len(), 2);
+
+                let resource = bundle.node("CAB-0123.resS").unwrap();
+                assert!(!resource.is_serialized_file());
+                assert_eq!(bundle.read_node(resource).unwrap(), b"pixels");
+
+                let node = bundle.node("CAB-0123").unwrap();
+                assert!(node.is_serialized_file());
+                let file = bundle.read_serialized_file(node).unwrap();
+                assert_eq!(file.unity_version, "2021.3.5f1");
+            }
+        }
+
+        // The level has to decompress to the size it claims
+        let mut bytes = legacy_bundle("UnityWeb", 3, nodes);
+        let last = bytes.len() - 1;
+        bytes.truncate(last);
+        assert!(Bundle::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn parse_unity_web_bundle_with_unity_fs_layout() {
+        let serialized_file = version_22_file();
+        for signature in [BundleSignature::UnityWeb, BundleSignature::UnityRaw] {
+            let unity_fs = compressed_bundle(
+                6,
+                0,
+                Compression::Lzma,
+                &[("CAB-0123", 4, &serialized_file)],
+            );
+            let mut bytes = format!("{}\0", signature.as_str()).into_bytes();
+            bytes.extend_from_slice(&unity_fs[b"UnityFS\0".len()..]);
+
+            let bundle = Bundle::from_bytes(&bytes).unwrap();
+            assert_eq!(bundle.header.signature, signature);
+            assert_eq!(bundle.header.version, 6);
+            assert!(bundle.levels.is_empty());
+            assert_eq!(bundle.blocks[0].compression(), Compression::Lzma);
+            let node = bundle.node("CAB-0123").unwrap();
+            assert!(node.is_serialized_file());
+            assert_eq!(bundle.read_node(node).unwrap(), serialized_file);
+        }
+    }
+
+    #[test]
+    fn parse_uncompressed_bundle() {
+        let serialized_file = version_22_file();
+        let nodes: &[(&str, u32, &[u8])] = &[
+            ("CAB-0123", 4, &serialized_file),
+            ("CAB-0123.resS", 0, b"pixels"),
+        ];
+
+        for (version, flags) in [(6, 0), (7, 0), (7, 0x80), (8, 0x200)] {
+            let bytes = uncompressed_bundle(version, flags, nodes);
+            let bundle = Bundle::from_bytes(&bytes).unwrap();
+            assert_eq!(bundle.header.signature, BundleSignature::UnityFS);
+            assert_eq!(bundle.header.version, version);
+            assert_eq!(bundle.header.engine_version, "2021.3.5f1");
+            assert_eq!(bundle.header.size, bytes.len() as u64);
+            assert_eq!(bundle.header.flags.compression(), Compression::None);
+            assert!(bundle.header.flags.blocks_and_directory_info_combined());
+            assert_eq!(bundle.blocks.len(), 1);
+            assert_eq!(bundle.nodes.len(), 2);
+
+            let resource = bundle.node("CAB-0123.resS").unwrap();
+            assert!(!resource.is_serialized_file());
+            assert_eq!(bundle.read_node(resource).unwrap(), b"pixels");
+
+            let node = bundle.node("CAB-0123").unwrap();
+            assert!(node.is_serialized_file());
+            let file = bundle.read_serialized_file(node).unwrap();
+            assert_eq!(file.unity_version, "2021.3.5f1");
+        }
+    }
+
+    #[test]
+    fn parse_compressed_bundle() {
+        let serialized_file = version_22_file();
+        let nodes: &[(&str, u32, &[u8])] = &[("CAB-0123", 4, &serialized_file)];
+
+        for compression in [Compression::Lzma, Compression::Lz4, Compression::Lz4HC] {
+            let bytes = compressed_bundle(7, 0, compression, nodes);
+            let bundle = Bundle::from_bytes(&bytes).unwrap();
+            assert_eq!(bundle.header.flags.compression(), compression);
+            assert_eq!(bundle.blocks[0].compression(), compression);
+            assert_eq!(
+                bundle.blocks[0].uncompressed_size as usize,
+                serialized_file.len()
+            );
+
+            let node = bundle.node("CAB-0123").unwrap();
+            assert_eq!(bundle.read_node(node).unwrap(), serialized_file);
+        }
+    }
+
+    #[test]
+    fn reject_lz4_size_mismatch() {
+        let serialized_file = version_22_file();
+        let bytes = compressed_bundle(7, 0, Compression::Lz4, &[("CAB-0123", 4, &serialized_file)]);
+        // Claim one byte more than the blocks info really decompresses to
+        let mut too_large = bytes.clone();
+        let uncompressed_size_offset = 8 + 4 + 6 + 11 + 8 + 4;
+        too_large[uncompressed_size_offset + 3] += 1;
+        assert!(Bundle::from_bytes(&too_large).is_err());
+
+        // Truncating the compressed data breaks the last block
+        assert!(Bundle::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    /// Indices of the blocks that are currently decompressed in the cache
+    fn cached_blocks(bundle: &Bundle) -> Vec<usize> {
+        let mut cache = bundle.cache.lock().unwrap();
+        (0..bundle.blocks.len())
+            .filter(|&index| cache.get(index).is_some())
+            .collect()
+    }
+
+    fn lz4_bundle_with_small_blocks(resource: &[u8]) -> Vec<u8> {
+        let serialized_file = version_22_file();
+        let mut writer = BundleWriter::new("2021.3.5f1", Compression::Lz4);
+        writer.block_size = 64;
+        let nodes = [
+            NodeData {
+                path: "CAB-0123",
+                flags: 4,
+                data: &serialized_file,
+            },
+            NodeData {
+                path: "CAB-0123.resS",
+                flags: 0,
+                data: resource,
+            },
+        ];
+        writer.to_bytes(&nodes).unwrap()
+    }
+
+    #[test]
+    fn decompress_only_the_blocks_read() {
+        let resource: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        let bundle = Bundle::from_bytes(&lz4_bundle_with_small_blocks(&resource)).unwrap();
+        assert!(cached_blocks(&bundle).is_empty());
+
+        let node = bundle.node("CAB-0123.resS").unwrap();
+        let bytes = bundle.read_node_range(node, 130, 10).unwrap();
+        assert_eq!(bytes, resource[130..140]);
+        let first = ((node.offset + 130) / 64) as usize;
+        let last = ((node.offset + 139) / 64) as usize;
+        assert_eq!(cached_blocks(&bundle), (first..=last).collect::<Vec<_>>());
+
+        // Reading across many blocks only keeps the most recent ones
+        bundle.set_cached_blocks(2);
+        assert_eq!(bundle.read_node(node).unwrap(), resource);
+        let blocks = bundle.blocks.len();
+        assert_eq!(cached_blocks(&bundle), [blocks - 2, blocks - 1]);
+
+        bundle.set_cached_blocks(0);
+        assert_eq!(bundle.read_node_range(node, 0, 1).unwrap(), resource[..1]);
+        assert!(cached_blocks(&bundle).is_empty());
+
+        assert!(bundle.read_node_range(node, 0, 0).unwrap().is_empty());
+        assert!(bundle.read_node_range(node, 999, 2).is_err());
+        assert!(bundle.read_node_range(node, u64::MAX, 2).is_err());
+    }
+
+    #[test]
+    fn read_blocks_from_the_opened_file() {
+        let resource: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        let directory = env::temp_dir().join(format!("disunity-bundle-{}", process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let bytes = lz4_bundle_with_small_blocks(&resource);
+        fs::write(directory.join("complete.bundle"), &bytes).unwrap();
+        fs::write(
+            directory.join("truncated.bundle"),
+            &bytes[..bytes.len() - 1],
+        )
+        .unwrap();
+        let bundle = Bundle::open(directory.join("complete.bundle"));
+        let truncated = Bundle::open(directory.join("truncated.bundle"));
+        fs::remove_dir_all(&directory).unwrap();
+
+        let bundle = bundle.unwrap();
+        let node = bundle.node("CAB-0123.resS").unwrap();
+        assert_eq!(
+            bundle.read_node_range(node, 500, 100).unwrap(),
+            resource[500..600]
+        );
+        assert_eq!(bundle.read_node(node).unwrap(), resource);
+        let node = bundle.node("CAB-0123").unwrap();
+        assert_eq!(
+            bundle.read_serialized_file(node).unwrap().unity_version,
+            "2021.3.5f1"
+        );
+
+        assert!(truncated.is_err());
+    }
+
+    #[test]
+    fn reject_node_outside_of_blocks() {
+        let bytes = uncompressed_bundle(6, 0, &[("CAB-0123", 4, b"data")]);
+        let mut bundle = Bundle::from_bytes(&bytes).unwrap();
+        bundle.nodes[0].size = 5;
+        assert!(bundle.read_node(&bundle.nodes[0]).is_err());
+        bundle.nodes[0].offset = u64::MAX;
+        assert!(bundle.read_node(&bundle.nodes[0]).is_err());
+    }
+
+    #[test]
+    fn reject_other_signatures() {
+        assert!(Bundle::from_bytes(b"UnityFX\0").is_err());
+        assert!(Bundle::from_bytes(b"UnityArchive\0\0\0\0\x01").is_err());
+    }
+}
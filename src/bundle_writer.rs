@@ -0,0 +1,265 @@
+use crate::{
+    compression::compress, error::ParserContext, Bundle, BundleFlags, BundleSignature, Compression,
+    ParseError, ParseResult,
+};
+use std::io::Write;
+
+/// A file to put in a bundle written by `BundleWriter`
+#[derive(Clone, Copy, Debug)]
+pub struct NodeData<'a> {
+    pub path: &'a str,
+    /// Same flags as `BundleNode::flags`, 0x4 for serialized files
+    pub flags: u32,
+    pub data: &'a [u8],
+}
+
+/// Writes UnityFS bundles, with the data of their nodes put back to back and split into blocks of
+/// `block_size` bytes that are each compressed with `compression`
+#[derive(Clone, Debug)]
+pub struct BundleWriter {
+    pub version: u32,
+    pub player_version: String,
+    pub engine_version: String,
+    /// Unity splits LZ4 bundles into blocks of 128 KiB so that they can be read piece by piece
+    /// and keeps LZMA bundles in a single block
+    pub block_size: u32,
+    /// `None`, `Lzma` or `Lz4`, there's no compressor to write `Lz4HC` with
+    pub compression: Compression,
+}
+
+impl BundleWriter {
+    pub const DEFAULT_BLOCK_SIZE: u32 = 0x20000;
+
+    /// A writer for bundles of format version 6, read by every Unity version since 5.3, with the
+    /// block size Unity uses for `compression`
+    pub fn new<S: Into<String>>(engine_version: S, compression: Compression) -> Self {
+        BundleWriter {
+            version: 6,
+            player_version: "5.x.x".to_string(),
+            engine_version: engine_version.into(),
+            block_size: match compression {
+                Compression::Lzma => u32::MAX,
+                _ => Self::DEFAULT_BLOCK_SIZE,
+            },
+            compression,
+        }
+    }
+
+    /// A writer for repacking `bundle`, keeping its format and Unity versions so that the game it
+    /// came from still accepts it
+    pub fn for_bundle(bundle: &Bundle, compression: Compression) -> Self {
+        let mut writer = Self::new(bundle.header.engine_version.clone(), compression);
+        if bundle.header.signature == BundleSignature::UnityFS {
+            writer.version = bundle.header.version;
+            writer.player_version = bundle.header.player_version.clone();
+        }
+
+        writer
+    }
+
+    /// Writes a bundle holding `nodes`, in order
+    pub fn write<W: Write>(&self, writer: &mut W, nodes: &[NodeData]) -> ParseResult<()> {
+        writer
+            .write_all(&self.to_bytes(nodes)?)
+            .context("writing bundle")
+    }
+
+    /// Lays out a bundle holding `nodes` in memory, its size has to be known before the header
+    /// can be written
+    pub fn to_bytes(&self, nodes: &[NodeData]) -> ParseResult<Vec<u8>> {
+        if self.block_size == 0 {
+            return Err(ParseError::expected(
+                "a block size larger than 0",
+                Vec::new(),
+                None,
+            ));
+        }
+
+        let mut data = Vec::new();
+        for node in nodes {
+            data.extend_from_slice(node.data);
+        }
+
+        let mut blocks_info = vec![0; 16];
+        let mut blocks = Vec::new();
+        let chunks = data.chunks(self.block_size as usize);
+        blocks_info.extend_from_slice(&(chunks.len() as u32).to_be_bytes());
+        for chunk in chunks {
+            let (compression, compressed) = self.compress_smaller(chunk)?;
+            blocks_info.extend_from_slice(&(chunk.len() as u32).to_be_bytes());
+            blocks_info.extend_from_slice(&(compressed.len() as u32).to_be_bytes());
+            blocks_info.extend_from_slice(&(compression.id() as u16).to_be_bytes());
+            blocks.push(compressed);
+        }
+
+        blocks_info.extend_from_slice(&(nodes.len() as u32).to_be_bytes());
+        let mut offset = 0u64;
+        for node in nodes {
+            blocks_info.extend_from_slice(&offset.to_be_bytes());
+            blocks_info.extend_from_slice(&(node.data.len() as u64).to_be_bytes());
+            blocks_info.extend_from_slice(&node.flags.to_be_bytes());
+            blocks_info.extend_from_slice(node.path.as_bytes());
+            blocks_info.push(0);
+            offset += node.data.len() as u64;
+        }
+        let (blocks_info_compression, compressed_blocks_info) =
+            self.compress_smaller(&blocks_info)?;
+
+        let mut bundle = b"UnityFS\0".to_vec();
+        bundle.extend_from_slice(&self.version.to_be_bytes());
+        bundle.extend_from_slice(self.player_version.as_bytes());
+        bundle.push(0);
+        bundle.extend_from_slice(self.engine_version.as_bytes());
+        bundle.push(0);
+        let size_offset = bundle.len();
+        bundle.extend_from_slice(&0u64.to_be_bytes());
+        bundle.extend_from_slice(&(compressed_blocks_info.len() as u32).to_be_bytes());
+        bundle.extend_from_slice(&(blocks_info.len() as u32).to_be_bytes());
+        let flags = blocks_info_compression.id() | BundleFlags::BLOCKS_AND_DIRECTORY_INFO_COMBINED;
+        bundle.extend_from_slice(&flags.to_be_bytes());
+        if self.version >= 7 {
+            bundle.resize(bundle.len().next_multiple_of(16), 0);
+        }
+
+        bundle.extend_from_slice(&compressed_blocks_info);
+        for block in blocks {
+            bundle.extend_from_slice(&block);
+        }
+
+        let size = bundle.len() as u64;
+        bundle[size_offset..size_offset + 8].copy_from_slice(&size.to_be_bytes());
+        Ok(bundle)
+    }
+
+    /// Compresses `data`, keeping it as is when compressing doesn't make it any smaller like
+    /// Unity does for data that is already compressed
+    fn compress_smaller(&self, data: &[u8]) -> ParseResult<(Compression, Vec<u8>)> {
+        let compressed = compress(self.compression, data)?;
+        if compressed.len() < data.len() {
+            Ok((self.compression, compressed))
+        } else {
+            Ok((Compression::None, data.to_vec()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BundleWriter, NodeData};
+    use crate::{bundle::tests::uncompressed_bundle, tests::version_22_file, Bundle, Compression};
+
+    fn nodes<'a>(serialized_file: &'a [u8], resource: &'a [u8]) -> [NodeData<'a>; 2] {
+        [
+            NodeData {
+                path: "CAB-0123",
+                flags: 4,
+                data: serialized_file,
+            },
+            NodeData {
+                path: "CAB-0123.resS",
+                flags: 0,
+                data: resource,
+            },
+        ]
+    }
+
+    #[test]
+    fn write_and_read_back() {
+        let serialized_file = version_22_file();
+        let resource = b"pixels".repeat(100);
+        let nodes = nodes(&serialized_file, &resource);
+        let data_len = serialized_file.len() + resource.len();
+
+        for compression in [Compression::None, Compression::Lzma, Compression::Lz4] {
+            for (version, block_size) in [(6, u32::MAX), (7, 64), (8, 100)] {
+                let mut writer = BundleWriter::new("2021.3.5f1", compression);
+                writer.version = version;
+                writer.block_size = block_size;
+                let mut bytes = Vec::new();
+                writer.write(&mut bytes, &nodes).unwrap();
+
+                let bundle = Bundle::from_bytes(&bytes).unwrap();
+                assert_eq!(bundle.header.version, version);
+                assert_eq!(bundle.header.engine_version, "2021.3.5f1");
+                assert_eq!(bundle.header.size, bytes.len() as u64);
+                assert!(bundle.header.flags.blocks_and_directory_info_combined());
+                assert_eq!(bundle.blocks.len(), data_len.div_ceil(block_size as usize));
+                let total: u64 = bundle
+                    .blocks
+                    .iter()
+                    .map(|block| u64::from(block.uncompressed_size))
+                    .sum();
+                assert_eq!(total, data_len as u64);
+
+                let node = bundle.node("CAB-0123").unwrap();
+                assert!(node.is_serialized_file());
+                assert_eq!(bundle.read_node(node).unwrap(), serialized_file);
+                let node = bundle.node("CAB-0123.resS").unwrap();
+                assert!(!node.is_serialized_file());
+                assert_eq!(bundle.read_node(node).unwrap(), resource);
+            }
+        }
+
+        // The repetitive resource compresses, so a single block holding it is compressed
+        for compression in [Compression::Lzma, Compression::Lz4] {
+            let mut writer = BundleWriter::new("2021.3.5f1", compression);
+            writer.block_size = resource.len() as u32;
+            let bytes = writer.to_bytes(&[nodes[1]]).unwrap();
+            assert!(bytes.len() < resource.len());
+            let bundle = Bundle::from_bytes(&bytes).unwrap();
+            assert_eq!(bundle.header.flags.compression(), compression);
+            assert_eq!(bundle.blocks[0].compression(), compression);
+            // While a byte on its own can't be made any smaller
+            writer.block_size = 1;
+            let bundle = Bundle::from_bytes(&writer.to_bytes(&[nodes[1]]).unwrap()).unwrap();
+            assert_eq!(bundle.blocks[0].compression(), Compression::None);
+        }
+    }
+
+    #[test]
+    fn repack_bundle() {
+        let serialized_file = version_22_file();
+        let original = uncompressed_bundle(7, 0, &[("CAB-0123", 4, &serialized_file)]);
+        let original = Bundle::from_bytes(&original).unwrap();
+
+        let edited = b"edited".to_vec();
+        let node = &original.nodes[0];
+        let writer = BundleWriter::for_bundle(&original, Compression::Lz4);
+        let bytes = writer
+            .to_bytes(&[NodeData {
+                path: &node.path,
+                flags: node.flags,
+                data: &edited,
+            }])
+            .unwrap();
+
+        let repacked = Bundle::from_bytes(&bytes).unwrap();
+        assert_eq!(repacked.header.version, original.header.version);
+        assert_eq!(
+            repacked.header.player_version,
+            original.header.player_version
+        );
+        assert_eq!(
+            repacked.header.engine_version,
+            original.header.engine_version
+        );
+        assert_eq!(repacked.nodes[0].path, node.path);
+        assert_eq!(repacked.nodes[0].flags, node.flags);
+        assert_eq!(repacked.read_node(&repacked.nodes[0]).unwrap(), edited);
+    }
+
+    #[test]
+    fn reject_unwritable_settings() {
+        let serialized_file = version_22_file();
+        let nodes = nodes(&serialized_file, b"pixels");
+
+        for compression in [Compression::Lz4HC, Compression::Lzham] {
+            let writer = BundleWriter::new("2021.3.5f1", compression);
+            assert!(writer.to_bytes(&nodes).is_err());
+        }
+
+        let mut writer = BundleWriter::new("2021.3.5f1", Compression::Lz4);
+        writer.block_size = 0;
+        assert!(writer.to_bytes(&nodes).is_err());
+    }
+}
@@ -0,0 +1,150 @@
+/// Unity's built-in buffer of common strings that type tree nodes can point into instead of
+/// their own string buffer, in buffer order with each string's offset being the sum of the
+/// lengths (plus null byte) of the ones before it.
+///
+/// Unity only ever appended to the end of the buffer, so every offset a file of any version can
+/// point to resolves to the same string in the buffer of the latest version.
+const COMMON_STRINGS: &[&str] = &[
+    "AABB",
+    "AnimationClip",
+    "AnimationCurve",
+    "AnimationState",
+    "Array",
+    "Base",
+    "BitField",
+    "bitset",
+    "bool",
+    "char",
+    "ColorRGBA",
+    "Component",
+    "data",
+    "deque",
+    "double",
+    "dynamic_array",
+    "FastPropertyName",
+    "first",
+    "float",
+    "Font",
+    "GameObject",
+    "Generic Mono",
+    "GradientNEW",
+    "GUID",
+    "GUIStyle",
+    "int",
+    "list",
+    "long long",
+    "map",
+    "Matrix4x4f",
+    "MdFour",
+    "MonoBehaviour",
+    "MonoScript",
+    "m_ByteSize",
+    "m_Curve",
+    "m_EditorClassIdentifier",
+    "m_EditorHideFlags",
+    "m_Enabled",
+    "m_ExtensionPtr",
+    "m_GameObject",
+    "m_Index",
+    "m_IsArray",
+    "m_IsStatic",
+    "m_MetaFlag",
+    "m_Name",
+    "m_ObjectHideFlags",
+    "m_PrefabInternal",
+    "m_PrefabParentObject",
+    "m_Script",
+    "m_StaticEditorFlags",
+    "m_Type",
+    "m_Version",
+    "Object",
+    "pair",
+    "PPtr<Component>",
+    "PPtr<GameObject>",
+    "PPtr<Material>",
+    "PPtr<MonoBehaviour>",
+    "PPtr<MonoScript>",
+    "PPtr<Object>",
+    "PPtr<Prefab>",
+    "PPtr<Sprite>",
+    "PPtr<TextAsset>",
+    "PPtr<Texture>",
+    "PPtr<Texture2D>",
+    "PPtr<Transform>",
+    "Prefab",
+    "Quaternionf",
+    "Rectf",
+    "RectInt",
+    "RectOffset",
+    "second",
+    "set",
+    "short",
+    "size",
+    "SInt16",
+    "SInt32",
+    "SInt64",
+    "SInt8",
+    "staticvector",
+    "string",
+    "TextAsset",
+    "TextMesh",
+    "Texture",
+    "Texture2D",
+    "Transform",
+    "TypelessData",
+    "UInt16",
+    "UInt32",
+    "UInt64",
+    "UInt8",
+    "unsigned int",
+    "unsigned long long",
+    "unsigned short",
+    "vector",
+    "Vector2f",
+    "Vector3f",
+    "Vector4f",
+    "m_ScriptingClassIdentifier",
+    "Gradient",
+    "Type*",
+    "int2_storage",
+    "int3_storage",
+    "BoundsInt",
+    "m_CorrespondingSourceObject",
+    "m_PrefabInstance",
+    "m_PrefabAsset",
+    "FileSize",
+    "Hash128",
+];
+
+/// Looks up the string starting at `offset` in the common strings buffer, the offset must already
+/// have its high bit cleared
+pub(crate) fn common_string(offset: u32) -> Option<&'static str> {
+    let mut string_offset = 0;
+    for string in COMMON_STRINGS {
+        if string_offset == offset {
+            return Some(string);
+        }
+        // This can't overflow since the buffer is only a bit over a kilobyte
+        string_offset += string.len() as u32 + 1;
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::common_string;
+
+    #[test]
+    fn offsets() {
+        assert_eq!(common_string(0), Some("AABB"));
+        assert_eq!(common_string(427), Some("m_Name"));
+        assert_eq!(common_string(981), Some("vector"));
+        assert_eq!(common_string(1042), Some("Gradient"));
+        assert_eq!(common_string(1093), Some("m_CorrespondingSourceObject"));
+        assert_eq!(common_string(1161), Some("Hash128"));
+        // Offsets have to point at the start of a string
+        assert_eq!(common_string(1), None);
+        assert_eq!(common_string(1169), None);
+    }
+}
@@ -0,0 +1,286 @@
+use crate::{error::ParserContext, ParseError, ParseResult};
+use disunity_derive::Variant;
+use std::io::Write;
+
+/// Compression of a bundle's blocks info or of one of its blocks, stored in the low 6 bits of
+/// their flags
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Variant)]
+#[disunity(discriminant = u32)]
+pub enum Compression {
+    Unknown(u32),
+    #[disunity(discriminant = 0)]
+    None,
+    #[disunity(discriminant = 1)]
+    Lzma,
+    #[disunity(discriminant = 2)]
+    Lz4,
+    #[disunity(discriminant = 3)]
+    Lz4HC,
+    #[disunity(discriminant = 4)]
+    Lzham,
+}
+
+/// Mask of the compression in block and blocks info flags
+const COMPRESSION_MASK: u32 = 0x3F;
+
+impl Compression {
+    pub(crate) fn from_flags(flags: u32) -> Self {
+        let value = flags & COMPRESSION_MASK;
+        CompressionVariant::from_int(value)
+            .and_then(Compression::from_variant)
+            .unwrap_or(Compression::Unknown(value))
+    }
+
+    /// The value stored in flags for this compression
+    pub(crate) fn id(self) -> u32 {
+        match self {
+            Compression::Unknown(value) => value,
+            Compression::None => CompressionVariant::None as u32,
+            Compression::Lzma => CompressionVariant::Lzma as u32,
+            Compression::Lz4 => CompressionVariant::Lz4 as u32,
+            Compression::Lz4HC => CompressionVariant::Lz4HC as u32,
+            Compression::Lzham => CompressionVariant::Lzham as u32,
+        }
+    }
+}
+
+/// Every byte of an LZ4 block adds at most 255 bytes to the length of a match
+const LZ4_MAX_RATIO: usize = 255;
+
+/// Decompresses data that has to come out as exactly `uncompressed_size` bytes
+pub(crate) fn decompress(
+    compression: Compression,
+    compressed: &[u8],
+    uncompressed_size: usize,
+) -> ParseResult<Vec<u8>> {
+    let decompressed = match compression {
+        Compression::None => compressed.to_vec(),
+        Compression::Lzma => decompress_lzma(compressed, uncompressed_size)?,
+        // LZ4HC only differs from LZ4 in how hard the compressor works, both are raw LZ4 blocks
+        // without a frame
+        Compression::Lz4 | Compression::Lz4HC => {
+            // The size comes from the block table, checking it against what the compressed data
+            // could possibly hold avoids allocating a corrupt size up front
+            if uncompressed_size > compressed.len().saturating_mul(LZ4_MAX_RATIO) {
+                return Err(ParseError::expected(
+                    format!("at most {LZ4_MAX_RATIO} times as many bytes once decompressed"),
+                    Vec::from((uncompressed_size as u64).to_le_bytes()),
+                    None,
+                ));
+            }
+            lz4_flex::block::decompress(compressed, uncompressed_size).map_err(|error| {
+                ParseError::expected(
+                    format!("valid LZ4 data ({error})"),
+                    compressed.iter().copied().take(16).collect(),
+                    None,
+                )
+            })?
+        }
+        unsupported => {
+            return Err(ParseError::expected(
+                format!("a supported compression instead of {unsupported:?}"),
+                Vec::new(),
+                None,
+            ))
+        }
+    };
+
+    check_decompressed_size(decompressed, uncompressed_size)
+}
+
+fn check_decompressed_size(
+    decompressed: Vec<u8>,
+    uncompressed_size: usize,
+) -> ParseResult<Vec<u8>> {
+    if decompressed.len() != uncompressed_size {
+        return Err(ParseError::expected(
+            format!("{uncompressed_size} bytes once decompressed"),
+            Vec::from((decompressed.len() as u64).to_le_bytes()),
+            None,
+        ));
+    }
+
+    Ok(decompressed)
+}
+
+/// Unity stores LZMA data as the 5 bytes of properties of the `.lzma` format without the 8 bytes
+/// of uncompressed size that follow them, the size comes from the block table instead
+fn decompress_lzma(compressed: &[u8], uncompressed_size: usize) -> ParseResult<Vec<u8>> {
+    lzma_with_unpacked_size(
+        compressed,
+        uncompressed_size,
+        lzma_rs::decompress::UnpackedSize::UseProvided(Some(uncompressed_size as u64)),
+    )
+}
+
+/// Decompresses a whole `.lzma` stream, with the 8 bytes of uncompressed size after its
+/// properties, like the data of UnityWeb bundles. The size in the stream is ignored in favor of
+/// `uncompressed_size` since some writers leave it unknown
+pub(crate) fn decompress_lzma_stream(
+    compressed: &[u8],
+    uncompressed_size: usize,
+) -> ParseResult<Vec<u8>> {
+    let decompressed = lzma_with_unpacked_size(
+        compressed,
+        uncompressed_size,
+        lzma_rs::decompress::UnpackedSize::ReadHeaderButUseProvided(Some(uncompressed_size as u64)),
+    )?;
+    check_decompressed_size(decompressed, uncompressed_size)
+}
+
+fn lzma_with_unpacked_size(
+    compressed: &[u8],
+    uncompressed_size: usize,
+    unpacked_size: lzma_rs::decompress::UnpackedSize,
+) -> ParseResult<Vec<u8>> {
+    let options = lzma_rs::decompress::Options {
+        unpacked_size,
+        // The dictionary never has to hold more than the whole output, no matter how large the
+        // properties claim it is
+        memlimit: Some(uncompressed_size),
+        ..Default::default()
+    };
+    // The size comes from the block table and LZMA can shrink data by far more than any bound
+    // worth checking, so the output grows with what the data actually decompresses to instead of
+    // allocating a corrupt size up front, like the dictionary does
+    let mut decompressed = Vec::new();
+    lzma_rs::lzma_decompress_with_options(&mut &compressed[..], &mut decompressed, &options)
+        .map_err(|error| {
+            ParseError::expected(
+                format!("valid LZMA data ({error})"),
+                compressed.iter().copied().take(16).collect(),
+                None,
+            )
+        })?;
+
+    Ok(decompressed)
+}
+
+/// Compresses data the way Unity stores it for `decompress` to read it back. LZ4HC is rejected
+/// rather than written by the fast LZ4 compressor under the LZ4HC flag, since there's no LZ4HC
+/// compressor to use
+pub(crate) fn compress(compression: Compression, data: &[u8]) -> ParseResult<Vec<u8>> {
+    match compression {
+        Compression::None => Ok(data.to_vec()),
+        Compression::Lz4 => Ok(lz4_flex::block::compress(data)),
+        Compression::Lzma => compress_lzma(data),
+        unsupported => Err(ParseError::expected(
+            format!("a compression that can be written instead of {unsupported:?}"),
+            Vec::new(),
+            None,
+        )),
+    }
+}
+
+/// lzma-rs only writes literals, so a real encoder is used for compressing while lzma-rs keeps
+/// decompressing since it rejects truncated data that lzma-rust2 silently pads
+fn compress_lzma(data: &[u8]) -> ParseResult<Vec<u8>> {
+    let mut options = lzma_rust2::LzmaOptions::with_preset(6);
+    // Readers allocate the whole dictionary up front, there's no use in it outgrowing the data
+    options.dict_size = options
+        .dict_size
+        .min((data.len() as u32).max(lzma_rust2::DICT_SIZE_MIN));
+
+    let mut compressed = vec![options.get_props()];
+    compressed.extend_from_slice(&options.dict_size.to_le_bytes());
+    let mut writer = lzma_rust2::LzmaWriter::new_no_header(compressed, &options, false)
+        .context("starting LZMA compression")?;
+    writer.write_all(data).context("compressing with LZMA")?;
+
+    writer.finish().context("finishing LZMA compression")
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::{compress, decompress, decompress_lzma_stream, Compression};
+
+    /// Compresses `data` as a whole `.lzma` stream for `decompress_lzma_stream` to read it back
+    pub(crate) fn compress_lzma_stream(data: &[u8]) -> Vec<u8> {
+        let mut compressed = compress(Compression::Lzma, data).unwrap();
+        let size = (data.len() as u64).to_le_bytes();
+        compressed.splice(5..5, size);
+        compressed
+    }
+
+    #[test]
+    fn decompress_lz4() {
+        let data = b"block of data, block of data, block of data".repeat(4);
+        let compressed = lz4_flex::block::compress(&data);
+        assert!(compressed.len() < data.len());
+
+        for compression in [Compression::Lz4, Compression::Lz4HC] {
+            assert_eq!(
+                decompress(compression, &compressed, data.len()).unwrap(),
+                data
+            );
+            // The block table has to agree with the data
+            assert!(decompress(compression, &compressed, data.len() + 1).is_err());
+            assert!(decompress(compression, &compressed, data.len() - 1).is_err());
+        }
+        assert!(decompress(
+            Compression::Lz4,
+            &compressed[..compressed.len() - 1],
+            data.len()
+        )
+        .is_err());
+
+        // Corrupt sizes are rejected without being allocated, while data that compresses as well
+        // as LZ4 allows still fits
+        assert!(decompress(Compression::Lz4, &compressed, u32::MAX as usize).is_err());
+        let zeros = vec![0; 1 << 20];
+        let compressed = lz4_flex::block::compress(&zeros);
+        assert!(compressed.len() * 250 < zeros.len());
+        assert_eq!(
+            decompress(Compression::Lz4, &compressed, zeros.len()).unwrap(),
+            zeros
+        );
+    }
+
+    #[test]
+    fn decompress_lzma() {
+        let data = b"block of data, block of data, block of data".repeat(4);
+        let compressed = compress(Compression::Lzma, &data).unwrap();
+        // Properties byte for lc=3, lp=0, pb=2 then the dictionary size, with no size after it
+        assert_eq!(compressed[0], 0x5D);
+
+        assert_eq!(
+            decompress(Compression::Lzma, &compressed, data.len()).unwrap(),
+            data
+        );
+        assert!(decompress(Compression::Lzma, &compressed, data.len() + 1).is_err());
+        assert!(decompress(
+            Compression::Lzma,
+            &compressed[..compressed.len() / 2],
+            data.len()
+        )
+        .is_err());
+        assert!(decompress(Compression::Lzma, &compressed[..3], data.len()).is_err());
+        // A corrupt size runs out of data instead of being allocated
+        assert!(decompress(Compression::Lzma, &compressed, u32::MAX as usize).is_err());
+        assert!(decompress_lzma_stream(&compress_lzma_stream(&data), u32::MAX as usize).is_err());
+    }
+
+    #[test]
+    fn decompress_whole_lzma_stream() {
+        let data = b"streamed level, streamed level".repeat(4);
+        let compressed = compress_lzma_stream(&data);
+        assert_eq!(compressed[5..13], (data.len() as u64).to_le_bytes());
+
+        assert_eq!(
+            decompress_lzma_stream(&compressed, data.len()).unwrap(),
+            data
+        );
+        assert!(decompress_lzma_stream(&compressed, data.len() + 1).is_err());
+    }
+
+    #[test]
+    fn compression_from_flags() {
+        assert_eq!(Compression::from_flags(0x43), Compression::Lz4HC);
+        assert_eq!(Compression::from_flags(0x3F), Compression::Unknown(0x3F));
+        assert_eq!(Compression::Lzma.id(), 1);
+        assert_eq!(
+            Compression::from_flags(Compression::Lz4HC.id()),
+            Compression::Lz4HC
+        );
+    }
+}
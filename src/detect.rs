@@ -0,0 +1,175 @@
+use crate::{error::ParserContext, parse_header, ParseResult};
+use std::{
+    fs::File,
+    io::{BufReader, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+/// Kinds of Unity bundles, named after the signature at the start of their header
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BundleSignature {
+    /// Used since Unity 5.3
+    UnityFS,
+    /// LZMA compressed bundles before Unity 5.3
+    UnityWeb,
+    /// Uncompressed bundles before Unity 5.3
+    UnityRaw,
+    /// Rarely used archives from Unity 3 and 4 streamed scenes
+    UnityArchive,
+}
+
+impl BundleSignature {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BundleSignature::UnityFS => "UnityFS",
+            BundleSignature::UnityWeb => "UnityWeb",
+            BundleSignature::UnityRaw => "UnityRaw",
+            BundleSignature::UnityArchive => "UnityArchive",
+        }
+    }
+}
+
+const BUNDLE_SIGNATURES: [BundleSignature; 4] = [
+    BundleSignature::UnityFS,
+    BundleSignature::UnityWeb,
+    BundleSignature::UnityRaw,
+    BundleSignature::UnityArchive,
+];
+
+/// Signature of the container WebGL builds pack their `*_Data` files into
+const WEBGL_DATA_SIGNATURE: &[u8] = b"UnityWebData1.0\0";
+
+/// Serialized files saved as text by the editor, `.unity`, `.prefab` and `.asset` files among
+/// others, start with a YAML directive
+const YAML_SIGNATURE: &[u8] = b"%YAML";
+
+/// Latest format version that is believed to be a serialized file, everything above is more
+/// likely to be a different kind of file that happens to fit the header
+const NEWEST_PLAUSIBLE_VERSION: u32 = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileKind {
+    /// A binary serialized file with its format version
+    SerializedFile {
+        version: u32,
+    },
+    Bundle(BundleSignature),
+    /// A `.resS` or `.resource` file holding raw data referenced by serialized files, like
+    /// texture pixels or audio samples
+    ResourceStream,
+    /// A serialized file saved as YAML text by the editor
+    YamlSerializedFile,
+    /// The `.data` container of a WebGL build
+    WebGlData,
+    Unknown,
+}
+
+/// Finds out what kind of Unity file `reader` holds from its content alone, starting from the
+/// start of the reader. Resource streams have no header to recognize them by and are reported as
+/// `Unknown`, `detect_file` can tell them apart by their extension
+pub fn detect<R: Read + Seek>(reader: &mut R) -> ParseResult<FileKind> {
+    let len = reader
+        .seek(SeekFrom::End(0))
+        .context("seeking to the end of the file")?;
+    reader
+        .seek(SeekFrom::Start(0))
+        .context("seeking to the start of the file")?;
+    let mut start = Vec::new();
+    reader
+        .take(32)
+        .read_to_end(&mut start)
+        .context("reading the start of the file")?;
+
+    for signature in BUNDLE_SIGNATURES {
+        let name = signature.as_str().as_bytes();
+        if start.starts_with(name) && start.get(name.len()) == Some(&0) {
+            return Ok(FileKind::Bundle(signature));
+        }
+    }
+    if start.starts_with(WEBGL_DATA_SIGNATURE) {
+        return Ok(FileKind::WebGlData);
+    }
+    if start.starts_with(YAML_SIGNATURE) {
+        return Ok(FileKind::YamlSerializedFile);
+    }
+
+    reader
+        .seek(SeekFrom::Start(0))
+        .context("seeking to the start of the file")?;
+    // Serialized files have no signature, but a header that can be parsed and whose file size
+    // matches the real one is very unlikely to be anything else
+    if let Ok(header) = parse_header(reader) {
+        if header.version <= NEWEST_PLAUSIBLE_VERSION && header.file_size == len {
+            return Ok(FileKind::SerializedFile {
+                version: header.version,
+            });
+        }
+    }
+
+    Ok(FileKind::Unknown)
+}
+
+/// Finds out what kind of Unity file is at `path` like `detect`, falling back to the extension
+/// for resource streams since they have no header
+pub fn detect_file<P: AsRef<Path>>(path: P) -> ParseResult<FileKind> {
+    let path = path.as_ref();
+    let file = File::open(path).context("opening file to detect")?;
+    let kind = detect(&mut BufReader::new(file))?;
+
+    let is_resource_stream = path.extension().is_some_and(|extension| {
+        extension.eq_ignore_ascii_case("resS") || extension.eq_ignore_ascii_case("resource")
+    });
+    if kind == FileKind::Unknown && is_resource_stream {
+        return Ok(FileKind::ResourceStream);
+    }
+
+    Ok(kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{detect, BundleSignature, FileKind};
+    use crate::tests::version_22_file;
+    use std::io::Cursor;
+
+    fn detect_bytes(bytes: &[u8]) -> FileKind {
+        detect(&mut Cursor::new(bytes)).unwrap()
+    }
+
+    #[test]
+    fn detect_kinds() {
+        assert_eq!(
+            detect_bytes(&version_22_file()),
+            FileKind::SerializedFile { version: 22 }
+        );
+        assert_eq!(
+            detect_bytes(b"UnityFS\0\0\0\0\x075.x.x\0"),
+            FileKind::Bundle(BundleSignature::UnityFS)
+        );
+        assert_eq!(
+            detect_bytes(b"UnityWeb\0\0\0\0\x03"),
+            FileKind::Bundle(BundleSignature::UnityWeb)
+        );
+        assert_eq!(
+            detect_bytes(b"UnityWebData1.0\0\x20\0\0\0"),
+            FileKind::WebGlData
+        );
+        assert_eq!(
+            detect_bytes(b"%YAML 1.1\n%TAG !u! tag:unity3d.com,2011:\n"),
+            FileKind::YamlSerializedFile
+        );
+
+        assert_eq!(detect_bytes(b""), FileKind::Unknown);
+        assert_eq!(detect_bytes(&[0xFF; 64]), FileKind::Unknown);
+        // A serialized file that lost its end no longer matches its header
+        let truncated = version_22_file();
+        assert_eq!(
+            detect_bytes(&truncated[..truncated.len() - 1]),
+            FileKind::Unknown
+        );
+        // The endianness byte has to be a bool
+        let mut bytes = version_22_file();
+        bytes[16] = 7;
+        assert_eq!(detect_bytes(&bytes), FileKind::Unknown);
+    }
+}
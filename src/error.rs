@@ -1,4 +1,4 @@
-use std::{fmt, io};
+use std::{fmt, io, path::PathBuf};
 
 pub struct ExpectedError {
     pub(crate) expected: String,
@@ -112,6 +112,63 @@ impl<T> ParserContext<T> for io::Result<T> {
     }
 }
 
+/// Why a `PPtr` couldn't be turned into the entry it points at
+#[derive(Debug)]
+pub enum ResolveError {
+    /// The pointer has a path id of 0, which Unity uses for references to nothing
+    Null,
+    /// The file id is neither 0 nor one more than an index into the file's externals
+    FileIdOutOfRange {
+        file_id: i32,
+        externals_count: usize,
+    },
+    /// The file id names an external that wasn't loaded
+    ExternalNotLoaded(PathBuf),
+    /// The file id names an external that was found but couldn't be parsed
+    ExternalUnreadable { path: PathBuf, source: ParseError },
+    /// The target file has no object with the path id. `file` is `None` when the target is the
+    /// file holding the pointer
+    PathIdNotFound { path_id: i64, file: Option<PathBuf> },
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResolveError::Null => f.write_str("Pointer is null"),
+            ResolveError::FileIdOutOfRange {
+                file_id,
+                externals_count,
+            } => write!(
+                f,
+                "File id {file_id} is out of range for a file with {externals_count} externals"
+            ),
+            ResolveError::ExternalNotLoaded(path) => {
+                write!(f, "External file {} isn't loaded", path.display())
+            }
+            ResolveError::ExternalUnreadable { path, .. } => {
+                write!(f, "External file {} couldn't be parsed", path.display())
+            }
+            ResolveError::PathIdNotFound {
+                path_id,
+                file: Some(file),
+            } => write!(f, "Path id {path_id} isn't in {}", file.display()),
+            ResolveError::PathIdNotFound {
+                path_id,
+                file: None,
+            } => write!(f, "Path id {path_id} isn't in the current file"),
+        }
+    }
+}
+
+impl std::error::Error for ResolveError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ResolveError::ExternalUnreadable { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
 pub fn string_error_to_parse_error<'a>(
     expected: &'a str,
 ) -> impl Fn((io::Error, Vec<u8>)) -> ParseError + 'a {
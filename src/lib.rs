@@ -0,0 +1,1700 @@
+mod block_cache;
+mod bundle;
+mod bundle_writer;
+mod common_strings;
+mod compression;
+mod detect;
+mod error;
+mod manager;
+mod pptr;
+mod type_tree;
+mod unity_version;
+mod utils;
+mod validation;
+mod value;
+
+use disunity_derive::Variant;
+use error::{string_error_to_parse_error, ParserContext};
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Cursor, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+use type_tree::{
+    parse_legacy_type_tree, parse_managed_reference_type, parse_type_dependencies,
+    parse_type_tree_blob,
+};
+use utils::{BufReadExt, ReadExt, SeekExt};
+
+pub use bundle::{Bundle, BundleFlags, BundleHeader, BundleNode, LevelRange, StorageBlock};
+pub use bundle_writer::{BundleWriter, NodeData};
+pub use compression::Compression;
+pub use detect::{detect, detect_file, BundleSignature, FileKind};
+pub use error::{ExpectedError, ParseError, ParseResult, ResolveError, UnexpectedIoError};
+pub use manager::AssetsManager;
+pub use pptr::PPtr;
+pub use type_tree::{ManagedReferenceType, TypeTree, TypeTreeNode};
+pub use unity_version::{ReleaseType, UnityVersion};
+pub use validation::Diagnostic;
+pub use value::Value;
+
+#[cfg(target_pointer_width = "16")]
+compile_error!("disunity doesn't support 16-bit platforms");
+
+#[derive(Clone, Copy, Debug)]
+pub enum Endianess {
+    Big,
+    Little,
+}
+
+#[derive(Debug)]
+pub struct Header {
+    pub version: u32,
+    pub endianess: Endianess,
+    // reserved??
+    pub metadata: u32,
+    pub file_size: u64,
+    pub data_offset: u64,
+}
+
+/// Oldest format version supported by disunity, used by early Unity 2.x files
+const OLDEST_VERSION: u32 = 3;
+/// Format version used by Unity 4, it is the first one to store the metadata right after the
+/// header instead of at the end of the file
+const UNITY_4_VERSION: u32 = 9;
+/// Format version that widened the header's file size and data offset to 64-bit
+const BIG_FILE_VERSION: u32 = 22;
+
+impl Header {
+    /// Offset right after the metadata, which is the end of the file before format version 9
+    pub(crate) fn metadata_end(&self) -> u64 {
+        let header_size = match self.version {
+            BIG_FILE_VERSION.. => 48,
+            UNITY_4_VERSION.. => 20,
+            // Metadata sits at the end of older files
+            _ => self.file_size.saturating_sub(u64::from(self.metadata)),
+        };
+
+        header_size + u64::from(self.metadata)
+    }
+}
+
+fn is_supported_version(version: u32) -> bool {
+    version >= OLDEST_VERSION
+}
+
+fn parse_header<R: Read + Seek>(file: &mut R) -> ParseResult<Header> {
+    // Every version starts with these 4 fields, the big file support version keeps them around
+    // but they are superseded by wider ones at the end of its header
+    let metadata = file
+        .read_u32(Endianess::Big)
+        .context("reading header metadata")?;
+    let file_size = file
+        .read_u32(Endianess::Big)
+        .context("reading header file size")?;
+    let version = file
+        .read_u32(Endianess::Big)
+        .context("reading header version")?;
+    let data_offset = file
+        .read_u32(Endianess::Big)
+        .context("reading header data offset")?;
+
+    if !is_supported_version(version) {
+        return Err(ParseError::expected(
+            "a supported serialized file version (3 and above)",
+            Vec::from(version.to_be_bytes()),
+            None,
+        ));
+    }
+
+    // Older files keep their metadata at the end of the file and their header doesn't have an
+    // endianess, instead it's the first byte of the metadata
+    if version < UNITY_4_VERSION {
+        let metadata_start = match file_size.checked_sub(metadata) {
+            Some(metadata_start) => metadata_start,
+            None => {
+                return Err(ParseError::expected(
+                    "metadata size to be smaller than file size",
+                    Vec::from(metadata.to_be_bytes()),
+                    None,
+                ))
+            }
+        };
+        file.seek(SeekFrom::Start(metadata_start.into()))
+            .context("seeking to metadata at the end of the file")?;
+    }
+
+    let endianess = file.read_bool().context("reading endianess boolean")?;
+    let endianess = if endianess {
+        Endianess::Big
+    } else {
+        Endianess::Little
+    };
+
+    if version < UNITY_4_VERSION {
+        return Ok(Header {
+            version,
+            endianess,
+            metadata,
+            file_size: file_size.into(),
+            data_offset: data_offset.into(),
+        });
+    }
+
+    // Throw away "reserved" for now
+    file.skip(3).context("ignoring reserved bytes")?;
+
+    if version < BIG_FILE_VERSION {
+        return Ok(Header {
+            version,
+            endianess,
+            metadata,
+            file_size: file_size.into(),
+            data_offset: data_offset.into(),
+        });
+    }
+
+    let metadata = file
+        .read_u32(Endianess::Big)
+        .context("reading header metadata")?;
+    let file_size = file
+        .read_u64(Endianess::Big)
+        .context("reading header file size")?;
+    let data_offset = file
+        .read_u64(Endianess::Big)
+        .context("reading header data offset")?;
+
+    // Ignore 8 unknown bytes
+    file.skip(8).context("ignoring last 8 bytes of header")?;
+
+    Ok(Header {
+        version,
+        endianess,
+        metadata,
+        file_size,
+        data_offset,
+    })
+}
+
+fn parse_unity_version<R: BufRead>(file: &mut R) -> ParseResult<String> {
+    file.read_null_terminated_string()
+        .map_err(string_error_to_parse_error("Unity version"))
+}
+
+/// Unity's `BuildTarget`, the platform a file was built for. Variants are named after Unity's
+/// own names without their `Standalone` prefix.
+///
+/// The platform is only informational: every header has its own endianess byte so files are
+/// never decoded with a per platform default, and disunity doesn't decode textures so there's
+/// no swizzling to undo.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Variant)]
+#[disunity(discriminant = i32)]
+pub enum TargetPlatform {
+    Unknown(i32),
+    #[disunity(discriminant = 1)]
+    ValidPlayer,
+    #[disunity(discriminant = 2)]
+    OSX,
+    #[disunity(discriminant = 3)]
+    OSXPPC,
+    #[disunity(discriminant = 4)]
+    OSXIntel,
+    #[disunity(discriminant = 5)]
+    Windows,
+    #[disunity(discriminant = 6)]
+    WebPlayer,
+    #[disunity(discriminant = 7)]
+    WebPlayerStreamed,
+    #[disunity(discriminant = 8)]
+    Wii,
+    #[disunity(discriminant = 9)]
+    IOS,
+    #[disunity(discriminant = 10)]
+    PS3,
+    #[disunity(discriminant = 11)]
+    Xbox360,
+    #[disunity(discriminant = 12)]
+    Broadcom,
+    #[disunity(discriminant = 13)]
+    Android,
+    #[disunity(discriminant = 14)]
+    GLESEmu,
+    #[disunity(discriminant = 15)]
+    GLES20Emu,
+    #[disunity(discriminant = 16)]
+    NaCl,
+    #[disunity(discriminant = 17)]
+    Linux,
+    #[disunity(discriminant = 18)]
+    FlashPlayer,
+    #[disunity(discriminant = 19)]
+    Windows64,
+    #[disunity(discriminant = 20)]
+    WebGL,
+    #[disunity(discriminant = 21)]
+    WSAPlayer,
+    #[disunity(discriminant = 24)]
+    Linux64,
+    #[disunity(discriminant = 25)]
+    LinuxUniversal,
+    #[disunity(discriminant = 26)]
+    WP8Player,
+    #[disunity(discriminant = 27)]
+    OSXIntel64,
+    #[disunity(discriminant = 28)]
+    BlackBerry,
+    #[disunity(discriminant = 29)]
+    Tizen,
+    #[disunity(discriminant = 30)]
+    PSP2,
+    #[disunity(discriminant = 31)]
+    PS4,
+    #[disunity(discriminant = 32)]
+    PSM,
+    #[disunity(discriminant = 33)]
+    XboxOne,
+    #[disunity(discriminant = 34)]
+    SamsungTV,
+    #[disunity(discriminant = 35)]
+    N3DS,
+    #[disunity(discriminant = 36)]
+    WiiU,
+    #[disunity(discriminant = 37)]
+    TvOS,
+    #[disunity(discriminant = 38)]
+    Switch,
+    #[disunity(discriminant = 39)]
+    Lumin,
+    #[disunity(discriminant = 40)]
+    Stadia,
+    #[disunity(discriminant = 41)]
+    LinuxHeadlessSimulation,
+    #[disunity(discriminant = 42)]
+    GameCoreXboxSeries,
+    #[disunity(discriminant = 43)]
+    GameCoreXboxOne,
+    #[disunity(discriminant = 44)]
+    PS5,
+    #[disunity(discriminant = 45)]
+    EmbeddedLinux,
+    #[disunity(discriminant = 46)]
+    QNX,
+    #[disunity(discriminant = 47)]
+    VisionOS,
+}
+
+impl TargetPlatform {
+    /// Human readable name of the platform, for example "Nintendo Switch"
+    pub fn name(&self) -> &'static str {
+        match self {
+            TargetPlatform::Unknown(_) => "Unknown",
+            TargetPlatform::ValidPlayer => "Valid Player",
+            TargetPlatform::OSX => "macOS",
+            TargetPlatform::OSXPPC => "macOS PowerPC",
+            TargetPlatform::OSXIntel => "macOS Intel",
+            TargetPlatform::Windows => "Windows",
+            TargetPlatform::WebPlayer => "Web Player",
+            TargetPlatform::WebPlayerStreamed => "Web Player Streamed",
+            TargetPlatform::Wii => "Wii",
+            TargetPlatform::IOS => "iOS",
+            TargetPlatform::PS3 => "PlayStation 3",
+            TargetPlatform::Xbox360 => "Xbox 360",
+            TargetPlatform::Broadcom => "Broadcom",
+            TargetPlatform::Android => "Android",
+            TargetPlatform::GLESEmu => "GLES Emulation",
+            TargetPlatform::GLES20Emu => "GLES 2.0 Emulation",
+            TargetPlatform::NaCl => "Native Client",
+            TargetPlatform::Linux => "Linux",
+            TargetPlatform::FlashPlayer => "Flash Player",
+            TargetPlatform::Windows64 => "Windows 64-bit",
+            TargetPlatform::WebGL => "WebGL",
+            TargetPlatform::WSAPlayer => "Universal Windows Platform",
+            TargetPlatform::Linux64 => "Linux 64-bit",
+            TargetPlatform::LinuxUniversal => "Linux Universal",
+            TargetPlatform::WP8Player => "Windows Phone 8",
+            TargetPlatform::OSXIntel64 => "macOS Intel 64-bit",
+            TargetPlatform::BlackBerry => "BlackBerry",
+            TargetPlatform::Tizen => "Tizen",
+            TargetPlatform::PSP2 => "PlayStation Vita",
+            TargetPlatform::PS4 => "PlayStation 4",
+            TargetPlatform::PSM => "PlayStation Mobile",
+            TargetPlatform::XboxOne => "Xbox One",
+            TargetPlatform::SamsungTV => "Samsung TV",
+            TargetPlatform::N3DS => "Nintendo 3DS",
+            TargetPlatform::WiiU => "Wii U",
+            TargetPlatform::TvOS => "tvOS",
+            TargetPlatform::Switch => "Nintendo Switch",
+            TargetPlatform::Lumin => "Magic Leap",
+            TargetPlatform::Stadia => "Stadia",
+            TargetPlatform::LinuxHeadlessSimulation => "Linux Headless Simulation",
+            TargetPlatform::GameCoreXboxSeries => "Xbox Series X|S",
+            TargetPlatform::GameCoreXboxOne => "Xbox One (GameCore)",
+            TargetPlatform::PS5 => "PlayStation 5",
+            TargetPlatform::EmbeddedLinux => "Embedded Linux",
+            TargetPlatform::QNX => "QNX",
+            TargetPlatform::VisionOS => "visionOS",
+        }
+    }
+}
+
+impl From<i32> for TargetPlatform {
+    fn from(value: i32) -> Self {
+        TargetPlatformVariant::from_int(value)
+            .and_then(TargetPlatform::from_variant)
+            .unwrap_or(TargetPlatform::Unknown(value))
+    }
+}
+
+fn parse_target_platform<R: Read>(
+    file: &mut R,
+    endianess: Endianess,
+) -> ParseResult<TargetPlatform> {
+    let target_platform = file
+        .read_i32(endianess)
+        .context("reading target platform")?;
+
+    Ok(target_platform.into())
+}
+
+fn parse_type_tree_presence<R: Read>(file: &mut R, version: u32) -> ParseResult<bool> {
+    // Files before version 13 don't have this flag and always embed their type trees
+    if version < 13 {
+        return Ok(true);
+    }
+
+    file.read_bool().context("reading type tree status")
+}
+
+#[derive(Debug, Variant)]
+#[disunity(discriminant = i32)]
+pub enum AssetClass {
+    Unknown(i32),
+    #[disunity(discriminant = 1)]
+    GameObject,
+    #[disunity(discriminant = 4)]
+    Transform,
+    #[disunity(discriminant = 20)]
+    Camera,
+    #[disunity(discriminant = 21)]
+    Material,
+    #[disunity(discriminant = 23)]
+    MeshRenderer,
+    #[disunity(discriminant = 28)]
+    Texture2D,
+    #[disunity(discriminant = 33)]
+    MeshFilter,
+    #[disunity(discriminant = 43)]
+    Mesh,
+    #[disunity(discriminant = 48)]
+    Shader,
+    #[disunity(discriminant = 49)]
+    TextAsset,
+    #[disunity(discriminant = 50)]
+    RigidBody2D,
+    #[disunity(discriminant = 58)]
+    CircleCollider2D,
+    #[disunity(discriminant = 60)]
+    PolygonCollider2D,
+    #[disunity(discriminant = 61)]
+    BoxCollider2D,
+    #[disunity(discriminant = 62)]
+    PhysicsMaterial2D,
+    #[disunity(discriminant = 65)]
+    BoxCollider,
+    #[disunity(discriminant = 66)]
+    CompositeCollider2D,
+    #[disunity(discriminant = 68)]
+    EdgeCollider2D,
+    #[disunity(discriminant = 70)]
+    CapsuleCollider2D,
+    #[disunity(discriminant = 72)]
+    ComputeShader,
+    #[disunity(discriminant = 74)]
+    AnimationClip,
+    #[disunity(discriminant = 81)]
+    AudioListener,
+    #[disunity(discriminant = 82)]
+    AudioSource,
+    #[disunity(discriminant = 91)]
+    AnimatorController,
+    #[disunity(discriminant = 95)]
+    Animator,
+    #[disunity(discriminant = 114)]
+    MonoBehavior {
+        /// Not present before format version 13
+        script_id: Option<[u8; 16]>,
+    },
+    #[disunity(discriminant = 120)]
+    LineRenderer,
+    #[disunity(discriminant = 128)]
+    Font,
+    #[disunity(discriminant = 141)]
+    BuildSettings,
+    #[disunity(discriminant = 150)]
+    PreloadData,
+    #[disunity(discriminant = 198)]
+    ParticleSystem,
+    #[disunity(discriminant = 199)]
+    ParticleSystemRenderer,
+    #[disunity(discriminant = 210)]
+    SortingGroup,
+    #[disunity(discriminant = 212)]
+    SpriteRenderer,
+    #[disunity(discriminant = 213)]
+    Sprite,
+    #[disunity(discriminant = 221)]
+    AnimatorOverrideController,
+    #[disunity(discriminant = 222)]
+    CanvasRenderer,
+    #[disunity(discriminant = 223)]
+    Canvas,
+    #[disunity(discriminant = 224)]
+    RectTransform,
+    #[disunity(discriminant = 225)]
+    CanvasGroup,
+    #[disunity(discriminant = 320)]
+    PlayableDirector,
+    #[disunity(discriminant = 328)]
+    VideoPlayer,
+    #[disunity(discriminant = 331)]
+    SpriteMask,
+    #[disunity(discriminant = 19719996)]
+    TilemapCollider2D,
+    #[disunity(discriminant = 156049354)]
+    Grid,
+    #[disunity(discriminant = 483693784)]
+    TilemapRenderer,
+    #[disunity(discriminant = 687078895)]
+    SpriteAtlas,
+    #[disunity(discriminant = 1839735485)]
+    Tilemap,
+}
+
+#[derive(Debug)]
+pub struct AssetType {
+    pub class_id: i32,
+    pub class: AssetClass,
+    pub stripped: bool,
+    /// Index into the file's script types or -1 for types that aren't scripts. Not present
+    /// before format version 11, and only found in the index entries until version 17
+    pub script_type_index: Option<i16>,
+    /// Not present before format version 13
+    pub old_type_hash: Option<[u8; 16]>,
+    /// Only present when the file has type trees enabled
+    pub type_tree: Option<TypeTree>,
+    /// Class ids of the types this type depends on, only present for object types when the file
+    /// has type trees enabled since format version 21
+    pub type_dependencies: Vec<i32>,
+    /// Only present for managed reference types when the file has type trees enabled since
+    /// format version 21
+    pub managed_reference: Option<ManagedReferenceType>,
+}
+
+/// Serialized files have two lists of asset types, one for the objects in the file and since
+/// format version 20 a second one for the managed reference types used by objects through
+/// `[SerializeReference]` fields
+#[derive(Clone, Copy, PartialEq)]
+enum AssetTypeList {
+    Objects,
+    References,
+}
+
+fn parse_asset_types<R: BufRead>(
+    file: &mut R,
+    version: u32,
+    endianess: Endianess,
+    has_type_tree: bool,
+    list: AssetTypeList,
+) -> ParseResult<Vec<AssetType>> {
+    let count = file
+        .read_u32(endianess)
+        .context("reading asset types count")?;
+
+    (0..count)
+        .map(|_| parse_asset_type(file, version, endianess, has_type_tree, list))
+        .collect()
+}
+
+fn parse_asset_type<R: BufRead>(
+    file: &mut R,
+    version: u32,
+    endianess: Endianess,
+    has_type_tree: bool,
+    list: AssetTypeList,
+) -> ParseResult<AssetType> {
+    let class_id = file
+        .read_i32(endianess)
+        .context("reading asset type class_id")?;
+
+    let stripped = if version >= 16 {
+        file.read_bool().context("reading asset type is_stripped")?
+    } else {
+        false
+    };
+    let script_type_index = if version >= 17 {
+        let script_type_index = file
+            .read_i16(endianess)
+            .context("reading asset type script type index")?;
+        Some(script_type_index)
+    } else {
+        None
+    };
+
+    // Before version 16 script types were stored with negative class ids
+    let is_script = if version >= 16 {
+        class_id == 114
+    } else {
+        class_id < 0
+    };
+    let is_referenced_script =
+        list == AssetTypeList::References && script_type_index.is_some_and(|index| index >= 0);
+
+    let mut script_id = None;
+    let mut old_type_hash = None;
+    if version >= 13 {
+        if is_script || is_referenced_script {
+            let mut id = [0u8; 16];
+            file.read_exact(&mut id).context("reading script id")?;
+            script_id = Some(id);
+        }
+
+        let mut hash = [0u8; 16];
+        file.read_exact(&mut hash)
+            .context("reading old type hash")?;
+        old_type_hash = Some(hash);
+    }
+
+    let class = match AssetClassVariant::from_int(class_id) {
+        _ if is_script => AssetClass::MonoBehavior { script_id },
+        Some(AssetClassVariant::MonoBehavior) => AssetClass::MonoBehavior { script_id },
+        Some(known_class) => {
+            AssetClass::from_variant(known_class).expect("to have handled all variants with fields")
+        }
+        None => AssetClass::Unknown(class_id),
+    };
+
+    let mut type_tree = None;
+    let mut type_dependencies = Vec::new();
+    let mut managed_reference = None;
+    if has_type_tree {
+        if version >= 12 || version == 10 {
+            type_tree = Some(parse_type_tree_blob(file, version, endianess)?);
+        } else {
+            type_tree = Some(parse_legacy_type_tree(file, version, endianess)?);
+        }
+
+        if version >= 21 {
+            match list {
+                AssetTypeList::Objects => {
+                    type_dependencies = parse_type_dependencies(file, endianess)?;
+                }
+                AssetTypeList::References => {
+                    managed_reference = Some(parse_managed_reference_type(file)?);
+                }
+            }
+        }
+    }
+
+    Ok(AssetType {
+        class_id,
+        class,
+        stripped,
+        script_type_index,
+        old_type_hash,
+        type_tree,
+        type_dependencies,
+        managed_reference,
+    })
+}
+
+fn parse_big_id_presence<R: Read>(
+    file: &mut R,
+    version: u32,
+    endianess: Endianess,
+) -> ParseResult<bool> {
+    if !(7..14).contains(&version) {
+        return Ok(false);
+    }
+
+    let big_id_enabled = file.read_i32(endianess).context("reading big id enabled")?;
+    Ok(big_id_enabled != 0)
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct AssetEntry {
+    pub path_id: i64,
+    pub offset: u64,
+    pub size: u32,
+    /// Index of this entry's type inside of [`SerializedFile::asset_types`]
+    pub type_index: usize,
+}
+
+pub type AssetsIndex = Vec<AssetEntry>;
+
+fn parse_index<R: Read + Seek>(
+    file: &mut R,
+    header: &Header,
+    big_id_enabled: bool,
+    types: &mut [AssetType],
+) -> ParseResult<AssetsIndex> {
+    let version = header.version;
+    let endianess = header.endianess;
+
+    let count = file.read_u32(endianess).context("reading entry count")?;
+
+    (0..count)
+        .map(|_| {
+            let path_id = if version >= 14 {
+                file.align_4().context("aligning file reader")?;
+                file.read_i64(endianess).context("reading entry path id")?
+            } else if big_id_enabled {
+                file.read_i64(endianess).context("reading entry path id")?
+            } else {
+                let path_id = file.read_i32(endianess).context("reading entry path id")?;
+                i64::from(path_id)
+            };
+            let offset = if version >= BIG_FILE_VERSION {
+                file.read_u64(endianess).context("reading entry offset")?
+            } else {
+                let offset = file.read_u32(endianess).context("reading entry offset")?;
+                u64::from(offset)
+            };
+            let size = file.read_u32(endianess).context("reading entry size")?;
+            let ty = file.read_i32(endianess).context("reading entry type")?;
+
+            // Before version 16 entries referred to their type by class id rather than by index
+            let type_index = if version >= 16 {
+                usize::try_from(ty)
+                    .ok()
+                    .filter(|type_index| *type_index < types.len())
+            } else {
+                let _class_id = file.read_u16(endianess).context("reading entry class id")?;
+                types
+                    .iter()
+                    .position(|asset_type| asset_type.class_id == ty)
+            };
+            let type_index = match type_index {
+                Some(type_index) => type_index,
+                None => {
+                    return Err(ParseError::expected(
+                        "one of the file's asset types",
+                        Vec::from(ty.to_le_bytes()),
+                        None,
+                    ))
+                }
+            };
+
+            if version < 11 {
+                let _is_destroyed = file
+                    .read_u16(endianess)
+                    .context("reading entry is destroyed")?;
+            }
+
+            // Until asset types started storing it themselves in version 17 every entry had the
+            // script type index of its asset type
+            if (11..17).contains(&version) {
+                let script_type_index = file
+                    .read_i16(endianess)
+                    .context("reading entry script type index")?;
+                types[type_index].script_type_index = Some(script_type_index);
+            }
+
+            if version == 15 || version == 16 {
+                let _stripped = file.read_u8().context("reading entry stripped")?;
+            }
+
+            let offset = match offset.checked_add(header.data_offset) {
+                Some(offset) => offset,
+                None => {
+                    return Err(ParseError::expected(
+                        "an entry offset that fits in 64 bits once added to the data offset",
+                        Vec::from(offset.to_le_bytes()),
+                        None,
+                    ))
+                }
+            };
+
+            Ok(AssetEntry {
+                path_id,
+                offset,
+                size,
+                type_index,
+            })
+        })
+        .collect()
+}
+
+/// Reference to the `MonoScript` object describing the C# class of the scripted asset types
+/// that point at it through their script type index
+#[derive(Debug)]
+pub struct ScriptType {
+    /// Zero for this file, otherwise one more than an index into the file's externals
+    pub file_index: i32,
+    /// Path id of the `MonoScript` in the file picked by `file_index`
+    pub path_id: i64,
+}
+
+fn parse_script_types<R: Read + Seek>(
+    file: &mut R,
+    version: u32,
+    endianess: Endianess,
+) -> ParseResult<Vec<ScriptType>> {
+    let count = file
+        .read_u32(endianess)
+        .context("reading script types count")?;
+
+    (0..count)
+        .map(|_| {
+            let file_index = file
+                .read_i32(endianess)
+                .context("reading script type file index")?;
+            let path_id = if version >= 14 {
+                file.align_4().context("aligning file reader")?;
+                file.read_i64(endianess)
+                    .context("reading script type path id")?
+            } else {
+                let path_id = file
+                    .read_i32(endianess)
+                    .context("reading script type path id")?;
+                i64::from(path_id)
+            };
+
+            Ok(ScriptType {
+                file_index,
+                path_id,
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+pub struct External {
+    /// Path of the asset in the editor's project, almost always empty in builds and before
+    /// format version 6
+    pub asset_path: String,
+    /// Zero before format version 5
+    pub guid: u128,
+    /// Zero before format version 5
+    pub ty: u32,
+    pub path: PathBuf,
+}
+
+fn parse_externals<R: BufRead>(
+    file: &mut R,
+    version: u32,
+    endianess: Endianess,
+) -> ParseResult<Vec<External>> {
+    let count = file
+        .read_u32(endianess)
+        .context("reading script type count")?;
+
+    (0..count)
+        .map(|_| {
+            let asset_path = if version >= 6 {
+                file.read_null_terminated_string()
+                    .map_err(string_error_to_parse_error("external asset path"))?
+            } else {
+                String::new()
+            };
+            let (guid, ty) = if version >= 5 {
+                let guid = file.read_u128(endianess).context("reading external guid")?;
+                let ty = file.read_u32(endianess).context("reading external type")?;
+                (guid, ty)
+            } else {
+                (0, 0)
+            };
+            let path = file
+                .read_null_terminated_string()
+                .map_err(string_error_to_parse_error("external path"))?;
+            let path = PathBuf::from(path);
+
+            Ok(External {
+                asset_path,
+                guid,
+                ty,
+                path,
+            })
+        })
+        .collect()
+}
+
+fn parse_user_information<R: BufRead>(file: &mut R) -> ParseResult<String> {
+    file.read_null_terminated_string()
+        .map_err(string_error_to_parse_error("user information"))
+}
+
+/// Makes sure all of the metadata was parsed, the user information is the last thing in it
+fn check_metadata_end<R: Seek>(file: &mut R, header: &Header) -> ParseResult<()> {
+    let metadata_end = header.metadata_end();
+
+    let position = file.stream_position().context("reading metadata end")?;
+    if position != metadata_end {
+        return Err(ParseError::expected(
+            format!("metadata to end at {metadata_end}"),
+            Vec::from(position.to_le_bytes()),
+            None,
+        ));
+    }
+
+    Ok(())
+}
+
+/// Checks that an object lies inside of a file of `file_len` bytes, returning where it ends
+fn check_object_bounds(entry: &AssetEntry, file_len: u64) -> ParseResult<u64> {
+    match entry.offset.checked_add(u64::from(entry.size)) {
+        Some(end) if end <= file_len => Ok(end),
+        _ => Err(ParseError::expected(
+            format!(
+                "object {} at {} with {} bytes to fit in the {file_len} bytes of the file",
+                entry.path_id, entry.offset, entry.size
+            ),
+            Vec::new(),
+            None,
+        )),
+    }
+}
+
+/// A parsed Unity serialized file, such as `resources.assets`, `sharedassets0.assets` or
+/// `level0`
+#[derive(Debug)]
+pub struct SerializedFile {
+    pub header: Header,
+    /// Empty before format version 7
+    pub unity_version: String,
+    /// `unity_version` parsed, `None` when it's empty or couldn't be parsed
+    pub parsed_unity_version: Option<UnityVersion>,
+    /// Not present before format version 8
+    pub target_platform: Option<TargetPlatform>,
+    pub asset_types: Vec<AssetType>,
+    pub index: AssetsIndex,
+    /// Not present before format version 11
+    pub script_types: Vec<ScriptType>,
+    pub externals: Vec<External>,
+    /// Managed reference types used by `[SerializeReference]` fields, empty before format
+    /// version 20
+    pub ref_types: Vec<AssetType>,
+    /// Empty before format version 5
+    pub user_information: String,
+}
+
+impl SerializedFile {
+    /// Opens and parses the serialized file at `path`
+    pub fn open<P: AsRef<Path>>(path: P) -> ParseResult<Self> {
+        Self::open_with_unity_version(path, None)
+    }
+
+    /// Opens and parses the serialized file at `path`, see `parse_with_unity_version`
+    pub fn open_with_unity_version<P: AsRef<Path>>(
+        path: P,
+        unity_version: Option<UnityVersion>,
+    ) -> ParseResult<Self> {
+        let file = File::open(path).context("opening serialized file")?;
+        let mut file = BufReader::new(file);
+
+        Self::parse_with_unity_version(&mut file, unity_version)
+    }
+
+    /// Parses a serialized file held in memory, for example one extracted from a bundle
+    pub fn from_bytes(bytes: &[u8]) -> ParseResult<Self> {
+        Self::parse(&mut Cursor::new(bytes))
+    }
+
+    /// Parses a serialized file from any seekable reader, buffering it internally
+    pub fn from_reader<R: Read + Seek>(reader: R) -> ParseResult<Self> {
+        Self::parse(&mut BufReader::new(reader))
+    }
+
+    /// Parses a serialized file from a buffered seekable reader positioned at the start of the
+    /// file
+    pub fn parse<R: BufRead + Seek>(file: &mut R) -> ParseResult<Self> {
+        Self::parse_with_unity_version(file, None)
+    }
+
+    /// Parses a serialized file like `parse`, decoding it as `unity_version` when given instead
+    /// of the version written in the file. Stripped builds write 0.0.0 or nothing at all there,
+    /// the given version becomes the file's `parsed_unity_version`
+    pub fn parse_with_unity_version<R: BufRead + Seek>(
+        file: &mut R,
+        unity_version: Option<UnityVersion>,
+    ) -> ParseResult<Self> {
+        let unity_version_override = unity_version;
+        let header = parse_header(file)?;
+        let unity_version = if header.version >= 7 {
+            parse_unity_version(file)?
+        } else {
+            String::new()
+        };
+        let target_platform = if header.version >= 8 {
+            Some(parse_target_platform(file, header.endianess)?)
+        } else {
+            None
+        };
+
+        let has_type_tree = parse_type_tree_presence(file, header.version)?;
+        let parsed_unity_version = unity_version_override.or_else(|| unity_version.parse().ok());
+        let mut asset_types = parse_asset_types(
+            file,
+            header.version,
+            header.endianess,
+            has_type_tree,
+            AssetTypeList::Objects,
+        )?;
+        let big_id_enabled = parse_big_id_presence(file, header.version, header.endianess)?;
+        let index = parse_index(file, &header, big_id_enabled, &mut asset_types)?;
+        let script_types = if header.version >= 11 {
+            parse_script_types(file, header.version, header.endianess)?
+        } else {
+            Vec::new()
+        };
+        let externals = parse_externals(file, header.version, header.endianess)?;
+        let ref_types = if header.version >= 20 {
+            parse_asset_types(
+                file,
+                header.version,
+                header.endianess,
+                has_type_tree,
+                AssetTypeList::References,
+            )?
+        } else {
+            Vec::new()
+        };
+        let user_information = if header.version >= 5 {
+            parse_user_information(file)?
+        } else {
+            String::new()
+        };
+
+        check_metadata_end(file, &header)?;
+
+        Ok(SerializedFile {
+            header,
+            unity_version,
+            parsed_unity_version,
+            target_platform,
+            asset_types,
+            index,
+            script_types,
+            externals,
+            ref_types,
+            user_information,
+        })
+    }
+
+    /// Reads only the Unity version written at the start of a serialized file, which is empty
+    /// before format version 7
+    pub fn read_unity_version<R: BufRead + Seek>(file: &mut R) -> ParseResult<String> {
+        let header = parse_header(file)?;
+        if header.version >= 7 {
+            parse_unity_version(file)
+        } else {
+            Ok(String::new())
+        }
+    }
+
+    /// Returns the asset type of an entry from this file's index
+    pub fn asset_type(&self, entry: &AssetEntry) -> &AssetType {
+        &self.asset_types[entry.type_index]
+    }
+
+    /// Looks up the entry of the object with `path_id` in this file's index
+    pub fn entry(&self, path_id: i64) -> Option<&AssetEntry> {
+        self.index.iter().find(|entry| entry.path_id == path_id)
+    }
+
+    /// Returns the reference to the `MonoScript` describing a `MonoBehaviour` asset type of this
+    /// file, or `None` for other types
+    pub fn script_type(&self, asset_type: &AssetType) -> Option<&ScriptType> {
+        if !matches!(asset_type.class, AssetClass::MonoBehavior { .. }) {
+            return None;
+        }
+        let index = usize::try_from(asset_type.script_type_index?).ok()?;
+
+        self.script_types.get(index)
+    }
+
+    /// Reads the raw data of an object from this file's index. `file` has to be the same file
+    /// this was parsed from, the object has to fit inside of it
+    pub fn read_object_bytes<R: Read + Seek>(
+        &self,
+        file: &mut R,
+        entry: &AssetEntry,
+    ) -> ParseResult<Vec<u8>> {
+        let file_len = file
+            .seek(SeekFrom::End(0))
+            .context("seeking to the end of the file")?;
+        check_object_bounds(entry, file_len)?;
+
+        file.seek(SeekFrom::Start(entry.offset))
+            .context("seeking to object")?;
+        let mut object = vec![0; entry.size as usize];
+        file.read_exact(&mut object).context("reading object")?;
+
+        Ok(object)
+    }
+
+    /// Borrows the raw data of an object from this file's index out of the bytes this was parsed
+    /// from, the object has to fit inside of them
+    pub fn object_bytes<'a>(&self, bytes: &'a [u8], entry: &AssetEntry) -> ParseResult<&'a [u8]> {
+        let end = check_object_bounds(entry, bytes.len() as u64)?;
+
+        Ok(&bytes[entry.offset as usize..end as usize])
+    }
+
+    /// Reads an object from this file's index using the type tree of its asset type. `file` has
+    /// to be the same file this was parsed from
+    pub fn read_object<R: Read + Seek>(
+        &self,
+        file: &mut R,
+        entry: &AssetEntry,
+    ) -> ParseResult<Value> {
+        let type_tree = match &self.asset_type(entry).type_tree {
+            Some(type_tree) => type_tree,
+            None => {
+                return Err(ParseError::expected(
+                    "an asset type with a type tree",
+                    Vec::new(),
+                    None,
+                ))
+            }
+        };
+
+        let object = self.read_object_bytes(file, entry)?;
+
+        // Alignment inside of objects is relative to the start of the object
+        type_tree.read_value(&mut Cursor::new(object), self.header.endianess)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        AssetClass, Endianess, ReleaseType, SerializedFile, TargetPlatform, TypeTree, TypeTreeNode,
+        UnityVersion, Value,
+    };
+    use std::io::Cursor;
+
+    /// Little endian writer for building serialized file fixtures, aligning relative to where
+    /// the written bytes will end up in the file
+    struct FixtureWriter {
+        bytes: Vec<u8>,
+        start: usize,
+    }
+
+    impl FixtureWriter {
+        fn new(start: usize) -> Self {
+            FixtureWriter {
+                bytes: Vec::new(),
+                start,
+            }
+        }
+
+        fn bytes(&mut self, bytes: &[u8]) -> &mut Self {
+            self.bytes.extend_from_slice(bytes);
+            self
+        }
+
+        fn string(&mut self, string: &str) -> &mut Self {
+            self.bytes(string.as_bytes()).bytes(&[0])
+        }
+
+        fn u16(&mut self, value: u16) -> &mut Self {
+            self.bytes(&value.to_le_bytes())
+        }
+
+        fn i32(&mut self, value: i32) -> &mut Self {
+            self.bytes(&value.to_le_bytes())
+        }
+
+        fn u32(&mut self, value: u32) -> &mut Self {
+            self.bytes(&value.to_le_bytes())
+        }
+
+        fn i64(&mut self, value: i64) -> &mut Self {
+            self.bytes(&value.to_le_bytes())
+        }
+
+        fn align_4(&mut self) -> &mut Self {
+            while !(self.start + self.bytes.len()).is_multiple_of(4) {
+                self.bytes.push(0);
+            }
+            self
+        }
+    }
+
+    /// Builds a little endian serialized file of any format version with a GameObject, a
+    /// MonoBehaviour along with its script type and an external, following the changes Unity
+    /// made to the layout over the versions
+    fn fixture(version: u32) -> Vec<u8> {
+        let header_size = match version {
+            22.. => 48,
+            9.. => 20,
+            _ => 16,
+        };
+        // Older versions put their objects before their metadata
+        let objects = [0u8; 8];
+        let metadata_start = if version >= 9 {
+            header_size
+        } else {
+            header_size + objects.len()
+        };
+
+        let mut metadata = FixtureWriter::new(metadata_start);
+        if version < 9 {
+            metadata.bytes(&[0]);
+        }
+        if version >= 7 {
+            metadata.string("5.6.7f1");
+        }
+        if version >= 8 {
+            metadata.i32(19);
+        }
+        if version >= 13 {
+            metadata.bytes(&[0]);
+        }
+
+        metadata.u32(2);
+        let script_class_id = if version >= 16 { 114 } else { -1 };
+        for (class_id, script_type_index) in [(1, u16::MAX), (script_class_id, 0)] {
+            metadata.i32(class_id);
+            if version >= 16 {
+                metadata.bytes(&[0]);
+            }
+            if version >= 17 {
+                metadata.u16(script_type_index);
+            }
+            if version >= 13 {
+                if class_id == script_class_id {
+                    metadata.bytes(&[0x11; 16]);
+                }
+                metadata.bytes(&[0x22; 16]);
+            } else if version >= 12 || version == 10 {
+                metadata.u32(1).u32(5).bytes(&[0; 24]).string("Base");
+            } else {
+                let fields_size = if version == 3 { 12 } else { 20 };
+                metadata
+                    .string("Base")
+                    .string("Base")
+                    .bytes(&vec![0; fields_size])
+                    .u32(0);
+            }
+        }
+
+        if (7..14).contains(&version) {
+            metadata.i32(0);
+        }
+
+        metadata.u32(2);
+        let entries = [(1, 0, 1, u16::MAX), (2, 1, script_class_id, 0)];
+        for (path_id, type_index, class_id, script_type_index) in entries {
+            if version >= 14 {
+                metadata.align_4().i64(path_id);
+            } else {
+                metadata.i32(path_id as i32);
+            }
+            let offset = 4 * type_index as u32;
+            if version >= 22 {
+                metadata.i64(offset.into());
+            } else {
+                metadata.u32(offset);
+            }
+            metadata.u32(4);
+            if version >= 16 {
+                metadata.i32(type_index);
+            } else {
+                metadata.i32(class_id);
+                metadata.u16(if class_id < 0 { 114 } else { class_id as u16 });
+            }
+            if version < 11 {
+                metadata.u16(0);
+            }
+            if (11..17).contains(&version) {
+                metadata.u16(script_type_index);
+            }
+            if version == 15 || version == 16 {
+                metadata.bytes(&[0]);
+            }
+        }
+
+        if version >= 11 {
+            metadata.u32(1).i32(1);
+            if version >= 14 {
+                metadata.align_4().i64(2);
+            } else {
+                metadata.i32(2);
+            }
+        }
+
+        metadata.u32(1);
+        // Builds almost always leave the asset path empty, but nothing stops it from being set
+        if version >= 17 {
+            metadata.string("Assets/Default.asset");
+        } else if version >= 6 {
+            metadata.string("");
+        }
+        if version >= 5 {
+            metadata.bytes(&[0x33; 16]).i32(0);
+        }
+        metadata.string("library/unity default resources");
+
+        if version >= 20 {
+            metadata
+                .u32(1)
+                .i32(114)
+                .bytes(&[0])
+                .u16(0)
+                .bytes(&[0x44; 16])
+                .bytes(&[0x55; 16]);
+        }
+        if version >= 5 {
+            metadata.string("user info");
+        }
+
+        let metadata = metadata.bytes;
+        let metadata_size = metadata.len() as u32;
+        let file_size = (header_size + objects.len() + metadata.len()) as u32;
+        let data_offset = if version >= 9 {
+            header_size as u32 + metadata_size
+        } else {
+            header_size as u32
+        };
+
+        let mut file = Vec::new();
+        if version >= 22 {
+            file.extend_from_slice(&[0; 8]);
+        } else {
+            file.extend_from_slice(&metadata_size.to_be_bytes());
+            file.extend_from_slice(&file_size.to_be_bytes());
+        }
+        file.extend_from_slice(&version.to_be_bytes());
+        if version >= 22 {
+            file.extend_from_slice(&[0; 4]);
+        } else {
+            file.extend_from_slice(&data_offset.to_be_bytes());
+        }
+        if version >= 9 {
+            file.extend_from_slice(&[0; 4]);
+        }
+        if version >= 22 {
+            file.extend_from_slice(&metadata_size.to_be_bytes());
+            file.extend_from_slice(&u64::from(file_size).to_be_bytes());
+            file.extend_from_slice(&u64::from(data_offset).to_be_bytes());
+            file.extend_from_slice(&[0; 8]);
+        }
+
+        if version >= 9 {
+            file.extend_from_slice(&metadata);
+            file.extend_from_slice(&objects);
+        } else {
+            file.extend_from_slice(&objects);
+            file.extend_from_slice(&metadata);
+        }
+
+        file
+    }
+
+    #[test]
+    fn parse_every_version() {
+        for version in 3..=22 {
+            let file = match SerializedFile::from_bytes(&fixture(version)) {
+                Ok(file) => file,
+                Err(error) => panic!("failed to parse version {version}: {error:?}"),
+            };
+
+            assert_eq!(file.header.version, version);
+            assert_eq!(file.asset_types.len(), 2, "version {version}");
+            assert!(matches!(file.asset_types[0].class, AssetClass::GameObject));
+            match file.asset_types[1].class {
+                AssetClass::MonoBehavior { script_id } => {
+                    let expected_script_id = if version >= 13 {
+                        Some([0x11; 16])
+                    } else {
+                        None
+                    };
+                    assert_eq!(script_id, expected_script_id, "version {version}");
+                }
+                ref class => panic!("expected MonoBehaviour in version {version}, got {class:?}"),
+            }
+
+            // Versions before 13 always embed type trees
+            if version < 13 {
+                let type_tree = file.asset_types[0].type_tree.as_ref().unwrap();
+                assert_eq!(type_tree.nodes[0].ty, "Base", "version {version}");
+            }
+
+            let expected_script_type_index = if version >= 11 { Some(0) } else { None };
+            assert_eq!(
+                file.asset_types[1].script_type_index, expected_script_type_index,
+                "version {version}"
+            );
+
+            assert_eq!(file.index.len(), 2, "version {version}");
+            for (entry, (path_id, type_index)) in file.index.iter().zip([(1, 0), (2, 1)]) {
+                assert_eq!(entry.path_id, path_id, "version {version}");
+                assert_eq!(entry.type_index, type_index, "version {version}");
+                assert_eq!(
+                    entry.offset,
+                    file.header.data_offset + 4 * type_index as u64,
+                    "version {version}"
+                );
+            }
+
+            assert!(file.script_type(&file.asset_types[0]).is_none());
+            match file.script_type(&file.asset_types[1]) {
+                Some(script_type) => {
+                    assert!(version >= 11, "version {version}");
+                    assert_eq!(script_type.file_index, 1, "version {version}");
+                    assert_eq!(script_type.path_id, 2, "version {version}");
+                }
+                None => assert!(version < 11, "version {version}"),
+            }
+            let expected_script_types_len = if version >= 11 { 1 } else { 0 };
+            assert_eq!(
+                file.script_types.len(),
+                expected_script_types_len,
+                "version {version}"
+            );
+
+            assert_eq!(
+                file.externals[0].path.to_str(),
+                Some("library/unity default resources"),
+                "version {version}"
+            );
+            let expected_asset_path = if version >= 17 {
+                "Assets/Default.asset"
+            } else {
+                ""
+            };
+            assert_eq!(
+                file.externals[0].asset_path, expected_asset_path,
+                "version {version}"
+            );
+
+            if version >= 20 {
+                let ref_type = &file.ref_types[0];
+                assert_eq!(ref_type.script_type_index, Some(0));
+                match ref_type.class {
+                    AssetClass::MonoBehavior { script_id } => {
+                        assert_eq!(script_id, Some([0x44; 16]))
+                    }
+                    ref class => panic!("expected MonoBehaviour ref type, got {class:?}"),
+                }
+                assert_eq!(ref_type.old_type_hash, Some([0x55; 16]));
+            } else {
+                assert!(file.ref_types.is_empty(), "version {version}");
+            }
+
+            let expected_user_information = if version >= 5 { "user info" } else { "" };
+            assert_eq!(file.user_information, expected_user_information);
+        }
+    }
+
+    #[test]
+    fn reject_leftover_metadata() {
+        let mut bytes = version_22_file();
+        // Grow the metadata size so that it no longer ends with the user information
+        let metadata_size = u32::from_be_bytes(bytes[20..24].try_into().unwrap());
+        bytes[20..24].copy_from_slice(&(metadata_size + 4).to_be_bytes());
+
+        assert!(SerializedFile::from_bytes(&bytes).is_err());
+    }
+
+    /// Builds a minimal little endian serialized file of format version 0x16 with a single
+    /// GameObject and a single external
+    pub(crate) fn version_22_file() -> Vec<u8> {
+        version_22_file_with_unity_version("2021.3.5f1")
+    }
+
+    pub(crate) fn version_22_file_with_unity_version(unity_version: &str) -> Vec<u8> {
+        let mut metadata = Vec::new();
+        metadata.extend_from_slice(unity_version.as_bytes());
+        metadata.push(0);
+        metadata.extend_from_slice(&19i32.to_le_bytes());
+        metadata.push(0);
+
+        metadata.extend_from_slice(&1u32.to_le_bytes());
+        metadata.extend_from_slice(&1u32.to_le_bytes());
+        metadata.push(0);
+        metadata.extend_from_slice(&u16::MAX.to_le_bytes());
+        metadata.extend_from_slice(&[0xAA; 16]);
+
+        metadata.extend_from_slice(&1u32.to_le_bytes());
+        while (48 + metadata.len()) % 4 != 0 {
+            metadata.push(0);
+        }
+        metadata.extend_from_slice(&1u64.to_le_bytes());
+        metadata.extend_from_slice(&0u64.to_le_bytes());
+        metadata.extend_from_slice(&4u32.to_le_bytes());
+        metadata.extend_from_slice(&0u32.to_le_bytes());
+
+        metadata.extend_from_slice(&0u32.to_le_bytes());
+
+        metadata.extend_from_slice(&1u32.to_le_bytes());
+        metadata.push(0);
+        metadata.extend_from_slice(&0u128.to_le_bytes());
+        metadata.extend_from_slice(&0u32.to_le_bytes());
+        metadata.extend_from_slice(b"library/unity default resources\0");
+
+        metadata.extend_from_slice(&0u32.to_le_bytes());
+        metadata.push(0);
+
+        let data_offset = 48 + metadata.len() as u64;
+        let mut file = Vec::new();
+        file.extend_from_slice(&[0; 8]);
+        file.extend_from_slice(&22u32.to_be_bytes());
+        file.extend_from_slice(&[0; 4]);
+        file.push(0);
+        file.extend_from_slice(&[0; 3]);
+        file.extend_from_slice(&(metadata.len() as u32).to_be_bytes());
+        file.extend_from_slice(&(data_offset + 4).to_be_bytes());
+        file.extend_from_slice(&data_offset.to_be_bytes());
+        file.extend_from_slice(&[0; 8]);
+        file.extend_from_slice(&metadata);
+        file.extend_from_slice(&42i32.to_le_bytes());
+
+        file
+    }
+
+    fn legacy_type_tree_node(ty: &str, name: &str, children: u32) -> Vec<u8> {
+        let mut node = Vec::new();
+        node.extend_from_slice(ty.as_bytes());
+        node.push(0);
+        node.extend_from_slice(name.as_bytes());
+        node.push(0);
+        node.extend_from_slice(&[0; 20]);
+        node.extend_from_slice(&children.to_be_bytes());
+        node
+    }
+
+    /// Builds a minimal big endian serialized file of format version 0x09 with a single
+    /// GameObject whose asset type embeds a small type tree
+    fn version_9_file() -> Vec<u8> {
+        let mut metadata = Vec::new();
+        metadata.extend_from_slice(b"4.7.2f1\0");
+        metadata.extend_from_slice(&5i32.to_be_bytes());
+
+        metadata.extend_from_slice(&1u32.to_be_bytes());
+        metadata.extend_from_slice(&1i32.to_be_bytes());
+        metadata.extend_from_slice(&legacy_type_tree_node("GameObject", "Base", 1));
+        metadata.extend_from_slice(&legacy_type_tree_node("string", "m_Name", 0));
+
+        metadata.extend_from_slice(&0i32.to_be_bytes());
+
+        metadata.extend_from_slice(&1u32.to_be_bytes());
+        metadata.extend_from_slice(&(-3i32).to_be_bytes());
+        metadata.extend_from_slice(&0u32.to_be_bytes());
+        metadata.extend_from_slice(&4u32.to_be_bytes());
+        metadata.extend_from_slice(&1i32.to_be_bytes());
+        metadata.extend_from_slice(&1u16.to_be_bytes());
+        metadata.extend_from_slice(&0u16.to_be_bytes());
+
+        metadata.extend_from_slice(&0u32.to_be_bytes());
+        metadata.push(0);
+
+        let data_offset = 20 + metadata.len() as u32;
+        let mut file = Vec::new();
+        file.extend_from_slice(&(metadata.len() as u32).to_be_bytes());
+        file.extend_from_slice(&(data_offset + 4).to_be_bytes());
+        file.extend_from_slice(&9u32.to_be_bytes());
+        file.extend_from_slice(&data_offset.to_be_bytes());
+        file.push(1);
+        file.extend_from_slice(&[0; 3]);
+        file.extend_from_slice(&metadata);
+        file.extend_from_slice(&[0; 4]);
+
+        file
+    }
+
+    /// Builds a minimal little endian serialized file of format version 0x03 whose metadata
+    /// comes after its single object at the end of the file
+    fn version_3_file() -> Vec<u8> {
+        let mut metadata = vec![0];
+
+        metadata.extend_from_slice(&1u32.to_le_bytes());
+        metadata.extend_from_slice(&4i32.to_le_bytes());
+        metadata.extend_from_slice(b"Transform\0Base\0");
+        metadata.extend_from_slice(&[0; 12]);
+        metadata.extend_from_slice(&0u32.to_le_bytes());
+
+        metadata.extend_from_slice(&1u32.to_le_bytes());
+        metadata.extend_from_slice(&7i32.to_le_bytes());
+        metadata.extend_from_slice(&16u32.to_le_bytes());
+        metadata.extend_from_slice(&4u32.to_le_bytes());
+        metadata.extend_from_slice(&4i32.to_le_bytes());
+        metadata.extend_from_slice(&4u16.to_le_bytes());
+        metadata.extend_from_slice(&0u16.to_le_bytes());
+
+        metadata.extend_from_slice(&1u32.to_le_bytes());
+        metadata.extend_from_slice(b"library/unity default resources\0");
+
+        let mut file = Vec::new();
+        file.extend_from_slice(&(metadata.len() as u32).to_be_bytes());
+        file.extend_from_slice(&(16 + 4 + metadata.len() as u32).to_be_bytes());
+        file.extend_from_slice(&3u32.to_be_bytes());
+        file.extend_from_slice(&0u32.to_be_bytes());
+        file.extend_from_slice(&[0; 4]);
+        file.extend_from_slice(&metadata);
+
+        file
+    }
+
+    fn assert_version_22_file(file: &SerializedFile) {
+        assert_eq!(file.header.version, 22);
+        assert_eq!(file.unity_version, "2021.3.5f1");
+        assert_eq!(
+            file.parsed_unity_version,
+            Some(UnityVersion::new(2021, 3, 5, ReleaseType::Final, 1))
+        );
+        assert!(matches!(file.asset_types[0].class, AssetClass::GameObject));
+        assert_eq!(file.index.len(), 1);
+        assert_eq!(file.index[0].path_id, 1);
+        assert_eq!(file.index[0].offset, file.header.data_offset);
+        assert_eq!(file.index[0].size, 4);
+        assert_eq!(
+            file.externals[0].path.to_str(),
+            Some("library/unity default resources")
+        );
+    }
+
+    #[test]
+    fn parse_from_byte_slice() {
+        let bytes = version_22_file();
+        let file = SerializedFile::from_bytes(&bytes).unwrap();
+        assert_version_22_file(&file);
+    }
+
+    #[test]
+    fn parse_unity_4_file() {
+        let bytes = version_9_file();
+        let file = SerializedFile::from_bytes(&bytes).unwrap();
+
+        assert_eq!(file.header.version, 9);
+        assert!(matches!(file.header.endianess, Endianess::Big));
+        assert_eq!(file.header.file_size, bytes.len() as u64);
+        assert_eq!(file.unity_version, "4.7.2f1");
+        assert_eq!(file.asset_types.len(), 1);
+        assert!(matches!(file.asset_types[0].class, AssetClass::GameObject));
+        let type_tree = file.asset_types[0].type_tree.as_ref().unwrap();
+        assert_eq!(type_tree.nodes.len(), 2);
+        assert_eq!(type_tree.nodes[1].name, "m_Name");
+        assert_eq!(type_tree.nodes[1].level, 1);
+        assert_eq!(file.index[0].path_id, -3);
+        assert_eq!(file.index[0].offset, file.header.data_offset);
+        assert!(file.script_types.is_empty());
+        assert!(file.externals.is_empty());
+    }
+
+    #[test]
+    fn parse_legacy_file_with_metadata_at_the_end() {
+        let file = SerializedFile::from_bytes(&version_3_file()).unwrap();
+
+        assert_eq!(file.header.version, 3);
+        assert!(matches!(file.header.endianess, Endianess::Little));
+        assert_eq!(file.unity_version, "");
+        assert_eq!(file.parsed_unity_version, None);
+        assert!(file.target_platform.is_none());
+        assert!(matches!(file.asset_types[0].class, AssetClass::Transform));
+        let type_tree = file.asset_types[0].type_tree.as_ref().unwrap();
+        assert_eq!(type_tree.nodes[0].ty, "Transform");
+        assert_eq!(file.index[0].path_id, 7);
+        assert_eq!(file.index[0].offset, 16);
+        assert_eq!(file.index[0].size, 4);
+        assert_eq!(file.externals[0].guid, 0);
+        assert_eq!(
+            file.externals[0].path.to_str(),
+            Some("library/unity default resources")
+        );
+    }
+
+    #[test]
+    fn reject_unsupported_version() {
+        let mut bytes = version_9_file();
+        bytes[8..12].copy_from_slice(&2u32.to_be_bytes());
+        assert!(SerializedFile::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn parse_from_cursor() {
+        let file = SerializedFile::from_reader(Cursor::new(version_22_file())).unwrap();
+        assert_version_22_file(&file);
+    }
+
+    #[test]
+    fn override_stripped_unity_version() {
+        let bytes = version_22_file_with_unity_version("0.0.0");
+        assert_eq!(
+            SerializedFile::read_unity_version(&mut Cursor::new(&bytes)).unwrap(),
+            "0.0.0"
+        );
+
+        let file = SerializedFile::from_bytes(&bytes).unwrap();
+        assert!(file.parsed_unity_version.unwrap().is_stripped());
+
+        let unity_version = UnityVersion::new(2021, 3, 5, ReleaseType::Final, 1);
+        let file =
+            SerializedFile::parse_with_unity_version(&mut Cursor::new(&bytes), Some(unity_version))
+                .unwrap();
+        assert_eq!(file.unity_version, "0.0.0");
+        assert_eq!(file.parsed_unity_version, Some(unity_version));
+    }
+
+    #[test]
+    fn target_platforms() {
+        let platform = TargetPlatform::from(19);
+        assert_eq!(platform, TargetPlatform::Windows64);
+        assert_eq!(platform.name(), "Windows 64-bit");
+        assert_eq!(TargetPlatform::from(38).name(), "Nintendo Switch");
+        assert_eq!(TargetPlatform::from(22), TargetPlatform::Unknown(22));
+        assert_eq!(TargetPlatform::from(-2), TargetPlatform::Unknown(-2));
+    }
+
+    #[test]
+    fn read_object_bytes() {
+        let bytes = version_22_file();
+        let file = SerializedFile::from_bytes(&bytes).unwrap();
+        let mut entry = file.index[0];
+
+        let expected = 42i32.to_le_bytes();
+        assert_eq!(file.object_bytes(&bytes, &entry).unwrap(), expected);
+        assert_eq!(
+            file.read_object_bytes(&mut Cursor::new(&bytes), &entry)
+                .unwrap(),
+            expected
+        );
+
+        entry.size += 1;
+        assert!(file.object_bytes(&bytes, &entry).is_err());
+        assert!(file
+            .read_object_bytes(&mut Cursor::new(&bytes), &entry)
+            .is_err());
+
+        entry.offset = u64::MAX;
+        assert!(file.object_bytes(&bytes, &entry).is_err());
+    }
+
+    #[test]
+    fn read_object_with_type_tree() {
+        let bytes = version_22_file();
+        let mut file = SerializedFile::from_bytes(&bytes).unwrap();
+        let entry = &file.index[0];
+        assert!(file.read_object(&mut Cursor::new(&bytes), entry).is_err());
+
+        let node = |level, ty: &str, name: &str| TypeTreeNode {
+            version: 1,
+            level,
+            type_flags: 0,
+            ty: ty.to_owned(),
+            name: name.to_owned(),
+            byte_size: 4,
+            index: 0,
+            meta_flags: 0,
+            ref_type_hash: None,
+        };
+        file.asset_types[0].type_tree = Some(TypeTree {
+            nodes: vec![node(0, "GameObject", "Base"), node(1, "int", "m_Value")],
+        });
+
+        let entry = &file.index[0];
+        let object = file.read_object(&mut Cursor::new(&bytes), entry).unwrap();
+        assert_eq!(object.field("m_Value"), Some(&Value::Int(42)));
+    }
+}
@@ -0,0 +1,607 @@
+use crate::{
+    detect_file,
+    error::{ParserContext, ResolveError},
+    utils::{BufReadExt, ReadExt},
+    AssetClass, AssetEntry, Bundle, BundleSignature, Endianess, External, FileKind, PPtr,
+    ParseResult, SerializedFile, UnityVersion,
+};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufReader, Cursor},
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+/// Loads the serialized files of a game and resolves the references between them.
+///
+/// Files are looked up by file name, ignoring case, since that's all externals can be matched
+/// on: an external of `library/unity default resources` is the `unity default resources` file in
+/// the game's `Resources` directory and one of `archive:/CAB-0123/CAB-0123` is the file named
+/// `CAB-0123` inside of a bundle. Files found on disk or inside of bundles are only parsed the
+/// first time they're needed, and parsed files are shared so the manager can be used from several
+/// threads.
+///
+/// Every file is decoded as the manager's Unity version when it has one, which is inferred when
+/// opening a directory so that stripped builds can be decoded, see `infer_unity_version`.
+#[derive(Debug, Default)]
+pub struct AssetsManager {
+    paths: HashMap<String, PathBuf>,
+    /// Serialized files inside of bundles, as the bundle and the index of their node
+    bundle_nodes: RwLock<HashMap<String, (Arc<Bundle>, usize)>>,
+    files: RwLock<HashMap<String, Arc<SerializedFile>>>,
+    unity_version: Option<UnityVersion>,
+}
+
+/// Lowercase file name used as the key of a file, `None` for paths without one
+fn file_key(path: &Path) -> Option<String> {
+    Some(path.file_name()?.to_str()?.to_lowercase())
+}
+
+/// Serialized files either have no extension, like `level0` and `globalgamemanagers`, or are
+/// `.assets` files. Everything else in a game's data directory is something else, like the
+/// `.resS` files holding texture data or the `Managed` assemblies
+fn may_be_serialized_file(path: &Path) -> bool {
+    match path.extension() {
+        None => true,
+        Some(extension) => extension.eq_ignore_ascii_case("assets"),
+    }
+}
+
+fn is_bundle(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| {
+        extension.eq_ignore_ascii_case("unity3d") || extension.eq_ignore_ascii_case("bundle")
+    })
+}
+
+/// Lists the files in a directory and its subdirectories, each directory's own files sorted by
+/// path and coming before the files of its subdirectories. Symbolic links to directories aren't
+/// followed since they could loop back to one of their parents
+fn walk_directory(directory: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries = fs::read_dir(directory)?
+        .map(|entry| entry.and_then(|entry| Ok((entry.path(), entry.file_type()?))))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let mut subdirectories = Vec::new();
+    for (path, file_type) in entries {
+        if file_type.is_dir() {
+            subdirectories.push(path);
+        } else if file_type.is_file() || (file_type.is_symlink() && path.is_file()) {
+            files.push(path);
+        }
+    }
+    for subdirectory in subdirectories {
+        walk_directory(&subdirectory, files)?;
+    }
+
+    Ok(())
+}
+
+fn known_version(unity_version: &str) -> Option<UnityVersion> {
+    unity_version
+        .parse::<UnityVersion>()
+        .ok()
+        .filter(|unity_version| !unity_version.is_stripped())
+}
+
+fn serialized_file_version(path: &Path) -> Option<UnityVersion> {
+    let mut file = BufReader::new(File::open(path).ok()?);
+    known_version(&SerializedFile::read_unity_version(&mut file).ok()?)
+}
+
+/// Reads the engine version from the header of a bundle, which starts with its signature,
+/// format version, player version and engine version
+fn bundle_version(path: &Path) -> Option<UnityVersion> {
+    let mut file = BufReader::new(File::open(path).ok()?);
+    let signature = file.read_null_terminated_string().ok()?;
+    if !signature.starts_with("Unity") {
+        return None;
+    }
+    file.read_u32(Endianess::Big).ok()?;
+    file.read_null_terminated_string().ok()?;
+
+    known_version(&file.read_null_terminated_string().ok()?)
+}
+
+/// The `BuildSettings` object of `globalgamemanagers` stores the version the game was built
+/// with as a string, which can be found without the type tree that stripped builds lack by
+/// looking for a length prefixed version at every aligned offset of the object
+fn build_settings_version(path: &Path) -> Option<UnityVersion> {
+    let file = SerializedFile::open(path).ok()?;
+    let mut reader = BufReader::new(File::open(path).ok()?);
+
+    file.index
+        .iter()
+        .filter(|entry| matches!(file.asset_type(entry).class, AssetClass::BuildSettings))
+        .filter_map(|entry| file.read_object_bytes(&mut reader, entry).ok())
+        .find_map(|object| find_version_string(&object, file.header.endianess))
+}
+
+fn find_version_string(object: &[u8], endianess: Endianess) -> Option<UnityVersion> {
+    (0..object.len()).step_by(4).find_map(|offset| {
+        let mut length = object.get(offset..offset + 4)?;
+        let length = length.read_u32(endianess).ok()? as usize;
+        // Versions are between "5.0.0f1" and something like "2022.3.10f1c1"
+        if !(7..=16).contains(&length) {
+            return None;
+        }
+        let string = object.get(offset + 4..offset + 4 + length)?;
+
+        known_version(std::str::from_utf8(string).ok()?)
+    })
+}
+
+impl AssetsManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Finds the serialized files in a game's `*_Data` directory and its subdirectories, such
+    /// as `Resources`, along with the ones inside of its bundles, and infers the game's Unity
+    /// version. Files directly inside of a directory take precedence over files with the same
+    /// name in its subdirectories, and files on disk over files inside of bundles. Bundles that
+    /// can't be read, like `UnityArchive` ones, are skipped
+    pub fn open_directory<P: AsRef<Path>>(directory: P) -> ParseResult<Self> {
+        let mut files = Vec::new();
+        walk_directory(directory.as_ref(), &mut files).context("reading game data directory")?;
+
+        let mut manager = Self::new();
+        manager.unity_version = Self::infer_unity_version(directory);
+        for path in files {
+            if may_be_serialized_file(&path) {
+                if let Some(key) = file_key(&path) {
+                    manager.paths.entry(key).or_insert(path);
+                }
+            } else if let Ok(FileKind::Bundle(
+                BundleSignature::UnityFS | BundleSignature::UnityWeb | BundleSignature::UnityRaw,
+            )) = detect_file(&path)
+            {
+                // A single broken bundle shouldn't keep the rest of the game from loading
+                if let Ok(bundle) = Bundle::open(&path) {
+                    manager.index_bundle(Arc::new(bundle), false);
+                }
+            }
+        }
+
+        Ok(manager)
+    }
+
+    /// Finds the Unity version of the game in a `*_Data` directory, even for stripped builds
+    /// that write 0.0.0 in place of the version of every serialized file. In order, the version
+    /// is taken from the first of:
+    ///
+    /// - a serialized file that isn't stripped, like the built-in `unity default resources`
+    /// - the `BuildSettings` object of `globalgamemanagers`
+    /// - the header of a bundle, like `data.unity3d`
+    ///
+    /// The player's `boot.config` and `ScriptingAssemblies.json` aren't looked at, the first only
+    /// holds player settings and the second the game's assemblies and their types, neither with
+    /// the version the game was built with.
+    pub fn infer_unity_version<P: AsRef<Path>>(directory: P) -> Option<UnityVersion> {
+        let mut files = Vec::new();
+        walk_directory(directory.as_ref(), &mut files).ok()?;
+        let file_name_is = |path: &Path, name: &str| {
+            path.file_name()
+                .is_some_and(|file_name| file_name.eq_ignore_ascii_case(name))
+        };
+
+        files
+            .iter()
+            .filter(|path| may_be_serialized_file(path))
+            .find_map(|path| serialized_file_version(path))
+            .or_else(|| {
+                files
+                    .iter()
+                    .filter(|path| file_name_is(path, "globalgamemanagers"))
+                    .find_map(|path| build_settings_version(path))
+            })
+            .or_else(|| {
+                files
+                    .iter()
+                    .filter(|path| is_bundle(path))
+                    .find_map(|path| bundle_version(path))
+            })
+    }
+
+    /// The Unity version every file is decoded as, `None` to use the version written in each
+    /// file
+    pub fn unity_version(&self) -> Option<UnityVersion> {
+        self.unity_version
+    }
+
+    /// Overrides the Unity version every file is decoded as. Files that were already parsed
+    /// from disk or out of a bundle are parsed again the next time they're needed, while files
+    /// added with `add_file` keep the version they were parsed with
+    pub fn set_unity_version(&mut self, unity_version: Option<UnityVersion>) {
+        self.unity_version = unity_version;
+        let paths = &self.paths;
+        let bundle_nodes = self.bundle_nodes.get_mut().unwrap();
+        self.files
+            .get_mut()
+            .unwrap()
+            .retain(|name, _| !paths.contains_key(name) && !bundle_nodes.contains_key(name));
+    }
+
+    /// Adds a file that was already parsed, replacing any file with the same name
+    pub fn add_file(&self, name: &str, file: SerializedFile) -> Arc<SerializedFile> {
+        let file = Arc::new(file);
+        let key = name.to_lowercase();
+        self.bundle_nodes.write().unwrap().remove(&key);
+        self.files.write().unwrap().insert(key, Arc::clone(&file));
+
+        file
+    }
+
+    /// Adds the serialized files of a bundle, named after their node paths that externals of
+    /// the form `archive:/CAB-0123/CAB-0123` refer to, replacing any file with the same name.
+    /// The bundle is kept so that its files can be parsed again
+    pub fn add_bundle(&self, bundle: Bundle) -> ParseResult<Vec<Arc<SerializedFile>>> {
+        let bundle = Arc::new(bundle);
+        self.index_bundle(Arc::clone(&bundle), true)
+            .into_iter()
+            .map(|(key, node)| self.parse_bundle_node(key, &bundle, node))
+            .collect()
+    }
+
+    /// Registers the serialized files of a bundle without parsing them, returning their names
+    /// and node indices. Files found on disk keep precedence over them unless `replace` is set
+    fn index_bundle(&self, bundle: Arc<Bundle>, replace: bool) -> Vec<(String, usize)> {
+        let mut bundle_nodes = self.bundle_nodes.write().unwrap();
+        let mut files = self.files.write().unwrap();
+        let mut indexed = Vec::new();
+        for (index, node) in bundle.nodes.iter().enumerate() {
+            let key = node.path.to_lowercase();
+            if !node.is_serialized_file() || (!replace && self.paths.contains_key(&key)) {
+                continue;
+            }
+            if replace {
+                files.remove(&key);
+            }
+            bundle_nodes.insert(key.clone(), (Arc::clone(&bundle), index));
+            indexed.push((key, index));
+        }
+
+        indexed
+    }
+
+    fn parse_bundle_node(
+        &self,
+        key: String,
+        bundle: &Bundle,
+        index: usize,
+    ) -> ParseResult<Arc<SerializedFile>> {
+        let bytes = bundle.read_node(&bundle.nodes[index])?;
+        let file = Arc::new(SerializedFile::parse_with_unity_version(
+            &mut Cursor::new(bytes),
+            self.unity_version,
+        )?);
+
+        Ok(self
+            .files
+            .write()
+            .unwrap()
+            .entry(key)
+            .or_insert(file)
+            .clone())
+    }
+
+    /// Names of every known file, lowercased, whether or not it was parsed yet
+    pub fn file_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.paths.keys().cloned().collect();
+        names.extend(self.bundle_nodes.read().unwrap().keys().cloned());
+        names.extend(self.files.read().unwrap().keys().cloned());
+        names.sort();
+        names.dedup();
+
+        names
+    }
+
+    /// Returns the file with the given name, parsing it on first use. Returns `Ok(None)` for
+    /// names the manager doesn't know about
+    pub fn file(&self, name: &str) -> ParseResult<Option<Arc<SerializedFile>>> {
+        let key = name.to_lowercase();
+        if let Some(file) = self.files.read().unwrap().get(&key) {
+            return Ok(Some(Arc::clone(file)));
+        }
+
+        // Parsing happens outside of the locks, another thread might parse the same file in the
+        // meantime in which case whichever finishes first is kept
+        let bundle_node = self.bundle_nodes.read().unwrap().get(&key).cloned();
+        if let Some((bundle, index)) = bundle_node {
+            return self.parse_bundle_node(key, &bundle, index).map(Some);
+        }
+        let path = match self.paths.get(&key) {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+        let file = Arc::new(SerializedFile::open_with_unity_version(
+            path,
+            self.unity_version,
+        )?);
+        let file = self
+            .files
+            .write()
+            .unwrap()
+            .entry(key)
+            .or_insert(file)
+            .clone();
+
+        Ok(Some(file))
+    }
+
+    /// Returns the file an external of another file refers to
+    pub fn external(&self, external: &External) -> ParseResult<Option<Arc<SerializedFile>>> {
+        match file_key(&external.path) {
+            Some(key) => self.file(&key),
+            None => Ok(None),
+        }
+    }
+
+    /// Finds the file and entry that a pointer held by `file` points at, loading the external
+    /// file it points into if needed
+    pub fn resolve(
+        &self,
+        file: &Arc<SerializedFile>,
+        pointer: PPtr,
+    ) -> Result<(Arc<SerializedFile>, AssetEntry), ResolveError> {
+        if pointer.is_null() {
+            return Err(ResolveError::Null);
+        }
+
+        let target = match pointer.external(file)? {
+            None => Arc::clone(file),
+            Some(external) => match self.external(external) {
+                Ok(Some(target)) => target,
+                Ok(None) => return Err(ResolveError::ExternalNotLoaded(external.path.clone())),
+                Err(source) => {
+                    return Err(ResolveError::ExternalUnreadable {
+                        path: external.path.clone(),
+                        source,
+                    })
+                }
+            },
+        };
+        let (_, entry) = pointer.resolve(file, |_| Some(&target))?;
+        let entry = *entry;
+
+        Ok((target, entry))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_version_string, AssetsManager};
+    use crate::{
+        bundle::tests::uncompressed_bundle,
+        tests::{version_22_file, version_22_file_with_unity_version},
+        Bundle, Endianess, External, PPtr, ReleaseType, ResolveError, SerializedFile, UnityVersion,
+    };
+    use std::{env, fs, io::Cursor, path::PathBuf, process, sync::Arc};
+
+    fn temp_directory(name: &str) -> PathBuf {
+        let directory = env::temp_dir().join(format!("disunity-{name}-{}", process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    #[test]
+    fn resolve_across_a_data_directory() {
+        let directory = temp_directory("manager");
+        fs::create_dir_all(directory.join("Resources")).unwrap();
+        fs::write(directory.join("level0"), version_22_file()).unwrap();
+        fs::write(
+            directory.join("Resources").join("unity default resources"),
+            version_22_file(),
+        )
+        .unwrap();
+        fs::write(directory.join("level0.resS"), b"not a serialized file").unwrap();
+
+        let manager = AssetsManager::open_directory(&directory).unwrap();
+        assert_eq!(manager.file_names(), ["level0", "unity default resources"]);
+        assert!(manager.file("level1").unwrap().is_none());
+
+        let level = manager.file("LEVEL0").unwrap().unwrap();
+        let pointer = PPtr {
+            file_id: 1,
+            path_id: 1,
+        };
+        let (target, entry) = manager.resolve(&level, pointer).unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+        assert!(!Arc::ptr_eq(&target, &level));
+        assert_eq!(entry.path_id, 1);
+        // Parsed files are cached
+        let resources = manager.file("unity default resources").unwrap().unwrap();
+        assert!(Arc::ptr_eq(&target, &resources));
+
+        let replacement = manager.add_file(
+            "unity default resources",
+            SerializedFile::from_bytes(&version_22_file()).unwrap(),
+        );
+        let (target, _) = manager.resolve(&level, pointer).unwrap();
+        assert!(Arc::ptr_eq(&target, &replacement));
+
+        let (target, _) = manager
+            .resolve(
+                &level,
+                PPtr {
+                    file_id: 0,
+                    path_id: 1,
+                },
+            )
+            .unwrap();
+        assert!(Arc::ptr_eq(&target, &level));
+
+        let pointer = PPtr {
+            file_id: 1,
+            path_id: 2,
+        };
+        assert!(matches!(
+            manager.resolve(&level, pointer),
+            Err(ResolveError::PathIdNotFound { path_id: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn infer_stripped_unity_version() {
+        let stripped = version_22_file_with_unity_version("0.0.0");
+        let unity_version = UnityVersion::new(2021, 3, 5, ReleaseType::Final, 1);
+
+        let directory = temp_directory("infer-resources");
+        fs::create_dir_all(directory.join("Resources")).unwrap();
+        fs::write(directory.join("level0"), &stripped).unwrap();
+        fs::write(
+            directory.join("Resources").join("unity default resources"),
+            version_22_file(),
+        )
+        .unwrap();
+        let mut manager = AssetsManager::open_directory(&directory).unwrap();
+        assert_eq!(manager.unity_version(), Some(unity_version));
+        let level = manager.file("level0").unwrap().unwrap();
+        assert_eq!(level.unity_version, "0.0.0");
+        assert_eq!(level.parsed_unity_version, Some(unity_version));
+
+        let overridden = UnityVersion::new(2021, 3, 6, ReleaseType::Final, 1);
+        manager.set_unity_version(Some(overridden));
+        let level = manager.file("level0").unwrap().unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(level.parsed_unity_version, Some(overridden));
+
+        let directory = temp_directory("infer-bundle");
+        fs::write(directory.join("level0"), &stripped).unwrap();
+        let mut bundle = b"UnityFS\0".to_vec();
+        bundle.extend_from_slice(&7u32.to_be_bytes());
+        bundle.extend_from_slice(b"5.x.x\x002020.3.1f1\0");
+        fs::write(directory.join("data.unity3d"), bundle).unwrap();
+        let inferred = AssetsManager::infer_unity_version(&directory);
+        fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(
+            inferred,
+            Some(UnityVersion::new(2020, 3, 1, ReleaseType::Final, 1))
+        );
+
+        let directory = temp_directory("infer-nothing");
+        fs::write(directory.join("level0"), &stripped).unwrap();
+        fs::write(
+            directory.join("boot.config"),
+            "gfx-enable-gfx-jobs=1\nwait-for-native-debugger=0\n",
+        )
+        .unwrap();
+        let inferred = AssetsManager::infer_unity_version(&directory);
+        fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(inferred, None);
+    }
+
+    #[test]
+    fn add_bundle() {
+        let serialized_file = version_22_file();
+        let bundle = uncompressed_bundle(6, 0, &[("CAB-0123", 4, &serialized_file)]);
+        let bundle = Bundle::from_bytes(&bundle).unwrap();
+
+        let mut manager = AssetsManager::new();
+        let files = manager.add_bundle(bundle).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(manager.file_names(), ["cab-0123"]);
+
+        let external = External {
+            asset_path: String::new(),
+            guid: 0,
+            ty: 0,
+            path: PathBuf::from("archive:/CAB-0123/CAB-0123"),
+        };
+        let file = manager.external(&external).unwrap().unwrap();
+        assert!(Arc::ptr_eq(&file, &files[0]));
+        assert_eq!(file.parsed_unity_version, "2021.3.5f1".parse().ok());
+
+        // Overriding the version parses the file out of the bundle again
+        let overridden = UnityVersion::new(2021, 3, 6, ReleaseType::Final, 1);
+        manager.set_unity_version(Some(overridden));
+        let file = manager.external(&external).unwrap().unwrap();
+        assert!(!Arc::ptr_eq(&file, &files[0]));
+        assert_eq!(file.parsed_unity_version, Some(overridden));
+
+        // Unlike a file that was added already parsed
+        let parsed = SerializedFile::parse(&mut Cursor::new(&serialized_file)).unwrap();
+        let added = manager.add_file("CAB-0123", parsed);
+        manager.set_unity_version(None);
+        let file = manager.external(&external).unwrap().unwrap();
+        assert!(Arc::ptr_eq(&file, &added));
+    }
+
+    #[test]
+    fn index_bundles_of_a_data_directory() {
+        let directory = temp_directory("manager-bundles");
+        let serialized_file = version_22_file();
+        fs::write(directory.join("level0"), &serialized_file).unwrap();
+        fs::write(
+            directory.join("data.unity3d"),
+            uncompressed_bundle(6, 0, &[("CAB-0123", 4, &serialized_file)]),
+        )
+        .unwrap();
+        // Same name as a file on disk, which wins
+        fs::write(
+            directory.join("other.bundle"),
+            uncompressed_bundle(6, 0, &[("level0", 4, b"not a serialized file")]),
+        )
+        .unwrap();
+        fs::write(directory.join("not-a.bundle"), b"something else").unwrap();
+        fs::write(directory.join("archive.bundle"), b"UnityArchive\0").unwrap();
+        fs::write(directory.join("truncated.bundle"), b"UnityFS\0\0\0\0\x06").unwrap();
+
+        let manager = AssetsManager::open_directory(&directory).unwrap();
+        assert_eq!(manager.file_names(), ["cab-0123", "level0"]);
+        assert!(manager.file("level0").unwrap().is_some());
+
+        let external = External {
+            asset_path: String::new(),
+            guid: 0,
+            ty: 0,
+            path: PathBuf::from("archive:/CAB-0123/CAB-0123"),
+        };
+        let file = manager.external(&external).unwrap().unwrap();
+        assert_eq!(file.unity_version, "2021.3.5f1");
+        assert!(Arc::ptr_eq(
+            &file,
+            &manager.external(&external).unwrap().unwrap()
+        ));
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn ignore_directory_symlinks() {
+        let directory = temp_directory("manager-symlinks");
+        fs::create_dir_all(directory.join("Resources")).unwrap();
+        fs::write(
+            directory.join("Resources").join("level0"),
+            version_22_file(),
+        )
+        .unwrap();
+        std::os::unix::fs::symlink(&directory, directory.join("Resources").join("loop")).unwrap();
+        std::os::unix::fs::symlink(
+            directory.join("Resources").join("level0"),
+            directory.join("level1"),
+        )
+        .unwrap();
+
+        let manager = AssetsManager::open_directory(&directory);
+        fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(manager.unwrap().file_names(), ["level0", "level1"]);
+    }
+
+    #[test]
+    fn find_build_settings_version() {
+        let mut object = Vec::new();
+        object.extend_from_slice(&1u32.to_le_bytes());
+        object.extend_from_slice(&5u32.to_le_bytes());
+        object.extend_from_slice(b"0.0.0\0\0\0");
+        object.extend_from_slice(&10u32.to_le_bytes());
+        object.extend_from_slice(b"2018.4.2f1\0\0");
+
+        assert_eq!(
+            find_version_string(&object, Endianess::Little),
+            Some(UnityVersion::new(2018, 4, 2, ReleaseType::Final, 1))
+        );
+        assert_eq!(find_version_string(&object[..20], Endianess::Little), None);
+    }
+}
@@ -0,0 +1,223 @@
+use crate::{error::ResolveError, AssetEntry, External, ScriptType, SerializedFile, Value};
+
+/// A reference from one Unity object to another, possibly in a different file
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PPtr {
+    /// Zero for the file holding the pointer, otherwise one more than an index into that file's
+    /// externals
+    pub file_id: i32,
+    /// Zero for pointers to nothing
+    pub path_id: i64,
+}
+
+impl PPtr {
+    /// Reads a pointer out of an object's `PPtr<...>` field
+    pub fn from_value(value: &Value) -> Option<Self> {
+        let file_id = match value.field("m_FileID")? {
+            Value::Int(file_id) => i32::try_from(*file_id).ok()?,
+            _ => return None,
+        };
+        let path_id = match value.field("m_PathID")? {
+            Value::Int(path_id) => *path_id,
+            _ => return None,
+        };
+
+        Some(PPtr { file_id, path_id })
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.path_id == 0
+    }
+
+    /// Returns the external file this points into, or `None` when it points into the file
+    /// holding it
+    pub fn external<'a>(
+        &self,
+        file: &'a SerializedFile,
+    ) -> Result<Option<&'a External>, ResolveError> {
+        if self.file_id == 0 {
+            return Ok(None);
+        }
+
+        usize::try_from(self.file_id)
+            .ok()
+            .and_then(|file_id| file.externals.get(file_id - 1))
+            .map(Some)
+            .ok_or(ResolveError::FileIdOutOfRange {
+                file_id: self.file_id,
+                externals_count: file.externals.len(),
+            })
+    }
+
+    /// Finds the file and entry this points at. `file` is the file holding the pointer and
+    /// `loaded_external` looks up the already loaded file of one of its externals
+    pub fn resolve<'a, F>(
+        &self,
+        file: &'a SerializedFile,
+        loaded_external: F,
+    ) -> Result<(&'a SerializedFile, &'a AssetEntry), ResolveError>
+    where
+        F: FnOnce(&External) -> Option<&'a SerializedFile>,
+    {
+        if self.is_null() {
+            return Err(ResolveError::Null);
+        }
+
+        let (target, target_path) = match self.external(file)? {
+            None => (file, None),
+            Some(external) => match loaded_external(external) {
+                Some(target) => (target, Some(&external.path)),
+                None => return Err(ResolveError::ExternalNotLoaded(external.path.clone())),
+            },
+        };
+
+        match target.entry(self.path_id) {
+            Some(entry) => Ok((target, entry)),
+            None => Err(ResolveError::PathIdNotFound {
+                path_id: self.path_id,
+                file: target_path.cloned(),
+            }),
+        }
+    }
+}
+
+impl From<&ScriptType> for PPtr {
+    fn from(script_type: &ScriptType) -> Self {
+        PPtr {
+            file_id: script_type.file_index,
+            path_id: script_type.path_id,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PPtr;
+    use crate::{AssetEntry, Endianess, External, Header, ResolveError, SerializedFile, Value};
+    use std::path::{Path, PathBuf};
+
+    fn serialized_file(path_ids: &[i64], externals: &[&str]) -> SerializedFile {
+        SerializedFile {
+            header: Header {
+                version: 22,
+                endianess: Endianess::Little,
+                metadata: 0,
+                file_size: 0,
+                data_offset: 0,
+            },
+            unity_version: String::new(),
+            parsed_unity_version: None,
+            target_platform: None,
+            asset_types: Vec::new(),
+            index: path_ids
+                .iter()
+                .map(|&path_id| AssetEntry {
+                    path_id,
+                    offset: 0,
+                    size: 0,
+                    type_index: 0,
+                })
+                .collect(),
+            script_types: Vec::new(),
+            externals: externals
+                .iter()
+                .map(|path| External {
+                    asset_path: String::new(),
+                    guid: 0,
+                    ty: 0,
+                    path: PathBuf::from(path),
+                })
+                .collect(),
+            ref_types: Vec::new(),
+            user_information: String::new(),
+        }
+    }
+
+    #[test]
+    fn resolve_across_files() {
+        let file = serialized_file(&[1, 2], &["sharedassets0.assets", "missing.assets"]);
+        let shared = serialized_file(&[7], &[]);
+        let loaded_external = |external: &External| {
+            (external.path == Path::new("sharedassets0.assets")).then_some(&shared)
+        };
+
+        let pointer = PPtr {
+            file_id: 0,
+            path_id: 2,
+        };
+        let (target, entry) = pointer.resolve(&file, loaded_external).unwrap();
+        assert!(std::ptr::eq(target, &file));
+        assert_eq!(entry.path_id, 2);
+
+        let pointer = PPtr {
+            file_id: 1,
+            path_id: 7,
+        };
+        let (target, entry) = pointer.resolve(&file, loaded_external).unwrap();
+        assert!(std::ptr::eq(target, &shared));
+        assert_eq!(entry.path_id, 7);
+
+        let pointer = PPtr {
+            file_id: 1,
+            path_id: 8,
+        };
+        assert!(matches!(
+            pointer.resolve(&file, loaded_external),
+            Err(ResolveError::PathIdNotFound {
+                path_id: 8,
+                file: Some(_)
+            })
+        ));
+
+        let pointer = PPtr {
+            file_id: 2,
+            path_id: 1,
+        };
+        assert!(matches!(
+            pointer.resolve(&file, loaded_external),
+            Err(ResolveError::ExternalNotLoaded(_))
+        ));
+
+        for file_id in [-1, 3] {
+            let pointer = PPtr {
+                file_id,
+                path_id: 1,
+            };
+            assert!(matches!(
+                pointer.resolve(&file, loaded_external),
+                Err(ResolveError::FileIdOutOfRange {
+                    externals_count: 2,
+                    ..
+                })
+            ));
+        }
+
+        let pointer = PPtr {
+            file_id: 0,
+            path_id: 0,
+        };
+        assert!(matches!(
+            pointer.resolve(&file, loaded_external),
+            Err(ResolveError::Null)
+        ));
+    }
+
+    #[test]
+    fn from_value() {
+        let value = Value::Struct {
+            ty: "PPtr<GameObject>".to_owned(),
+            fields: vec![
+                ("m_FileID".to_owned(), Value::Int(1)),
+                ("m_PathID".to_owned(), Value::Int(-5)),
+            ],
+        };
+        assert_eq!(
+            PPtr::from_value(&value),
+            Some(PPtr {
+                file_id: 1,
+                path_id: -5
+            })
+        );
+        assert_eq!(PPtr::from_value(&Value::Int(1)), None);
+    }
+}
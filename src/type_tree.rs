@@ -0,0 +1,437 @@
+use crate::{
+    common_strings::common_string,
+    error::{string_error_to_parse_error, ParseError, ParseResult, ParserContext},
+    utils::{BufReadExt, ReadExt},
+    Endianess,
+};
+use std::io::{BufRead, Read};
+
+/// Describes how an object of an asset type is laid out, nodes are stored depth first with each
+/// node followed by its children, which are the nodes right after it with a deeper level
+#[derive(Debug)]
+pub struct TypeTree {
+    pub nodes: Vec<TypeTreeNode>,
+}
+
+#[derive(Debug)]
+pub struct TypeTreeNode {
+    pub version: i32,
+    /// Depth of this node in the tree, the root node has a level of 0
+    pub level: u8,
+    /// Non-zero for arrays
+    pub type_flags: i32,
+    pub ty: String,
+    pub name: String,
+    /// Size in bytes of the type, or -1 for types with a variable size
+    pub byte_size: i32,
+    /// Zero in format version 3
+    pub index: i32,
+    /// Zero in format version 3
+    pub meta_flags: u32,
+    /// Not present before format version 19
+    pub ref_type_hash: Option<u64>,
+}
+
+/// Managed reference types name the C# class that they describe, present from format version 21
+#[derive(Debug)]
+pub struct ManagedReferenceType {
+    pub class_name: String,
+    pub namespace: String,
+    pub assembly_name: String,
+}
+
+struct RawNode {
+    version: u16,
+    level: u8,
+    type_flags: u8,
+    type_offset: u32,
+    name_offset: u32,
+    byte_size: i32,
+    index: i32,
+    meta_flags: u32,
+    ref_type_hash: Option<u64>,
+}
+
+/// Parses a type tree stored as an array of fixed size nodes followed by a buffer of the strings
+/// the nodes point into, used since format version 12 and by version 10
+pub(crate) fn parse_type_tree_blob<R: Read>(
+    file: &mut R,
+    version: u32,
+    endianess: Endianess,
+) -> ParseResult<TypeTree> {
+    let nodes_count = file
+        .read_u32(endianess)
+        .context("reading type tree nodes count")?;
+    let string_buffer_size = file
+        .read_u32(endianess)
+        .context("reading type tree string buffer size")?;
+
+    let raw_nodes = (0..nodes_count)
+        .map(|_| {
+            let node_version = file
+                .read_u16(endianess)
+                .context("reading type tree node version")?;
+            let level = file.read_u8().context("reading type tree node level")?;
+            let type_flags = file
+                .read_u8()
+                .context("reading type tree node type flags")?;
+            let type_offset = file
+                .read_u32(endianess)
+                .context("reading type tree node type offset")?;
+            let name_offset = file
+                .read_u32(endianess)
+                .context("reading type tree node name offset")?;
+            let byte_size = file
+                .read_i32(endianess)
+                .context("reading type tree node byte size")?;
+            let index = file
+                .read_i32(endianess)
+                .context("reading type tree node index")?;
+            let meta_flags = file
+                .read_u32(endianess)
+                .context("reading type tree node meta flags")?;
+            let ref_type_hash = if version >= 19 {
+                let ref_type_hash = file
+                    .read_u64(endianess)
+                    .context("reading type tree node ref type hash")?;
+                Some(ref_type_hash)
+            } else {
+                None
+            };
+
+            Ok(RawNode {
+                version: node_version,
+                level,
+                type_flags,
+                type_offset,
+                name_offset,
+                byte_size,
+                index,
+                meta_flags,
+                ref_type_hash,
+            })
+        })
+        .collect::<ParseResult<Vec<_>>>()?;
+
+    // Reading through `take` avoids allocating a corrupt size up front
+    let mut string_buffer = Vec::new();
+    file.by_ref()
+        .take(u64::from(string_buffer_size))
+        .read_to_end(&mut string_buffer)
+        .context("reading type tree string buffer")?;
+    if string_buffer.len() as u64 != u64::from(string_buffer_size) {
+        return Err(ParseError::expected(
+            format!("{string_buffer_size} bytes of type tree strings"),
+            Vec::from((string_buffer.len() as u64).to_le_bytes()),
+            None,
+        ));
+    }
+
+    let nodes = raw_nodes
+        .into_iter()
+        .map(|node| {
+            Ok(TypeTreeNode {
+                version: node.version.into(),
+                level: node.level,
+                type_flags: node.type_flags.into(),
+                ty: resolve_string(&string_buffer, node.type_offset)?,
+                name: resolve_string(&string_buffer, node.name_offset)?,
+                byte_size: node.byte_size,
+                index: node.index,
+                meta_flags: node.meta_flags,
+                ref_type_hash: node.ref_type_hash,
+            })
+        })
+        .collect::<ParseResult<Vec<_>>>()?;
+
+    Ok(TypeTree { nodes })
+}
+
+/// Parses a type tree stored in the recursive format used before format version 12 (except by
+/// version 10), each node has its strings inline and is followed by its children
+pub(crate) fn parse_legacy_type_tree<R: BufRead>(
+    file: &mut R,
+    version: u32,
+    endianess: Endianess,
+) -> ParseResult<TypeTree> {
+    let mut nodes = Vec::new();
+    // How many children are still left to read for each ancestor of the next node
+    let mut remaining_children = Vec::<u32>::new();
+
+    loop {
+        let level = match u8::try_from(remaining_children.len()) {
+            Ok(level) => level,
+            Err(_) => {
+                return Err(ParseError::expected(
+                    "a type tree at most 256 levels deep",
+                    Vec::new(),
+                    None,
+                ))
+            }
+        };
+
+        let (node, children_count) = parse_legacy_node(file, version, endianess, level)?;
+        nodes.push(node);
+
+        if children_count > 0 {
+            remaining_children.push(children_count);
+            continue;
+        }
+
+        // This node is a leaf so walk back up through the ancestors we finished reading
+        loop {
+            match remaining_children.last_mut() {
+                None => return Ok(TypeTree { nodes }),
+                Some(remaining) => {
+                    *remaining -= 1;
+                    if *remaining > 0 {
+                        break;
+                    }
+                    remaining_children.pop();
+                }
+            }
+        }
+    }
+}
+
+fn parse_legacy_node<R: BufRead>(
+    file: &mut R,
+    version: u32,
+    endianess: Endianess,
+    level: u8,
+) -> ParseResult<(TypeTreeNode, u32)> {
+    let ty = file
+        .read_null_terminated_string()
+        .map_err(string_error_to_parse_error("type tree node type"))?;
+    let name = file
+        .read_null_terminated_string()
+        .map_err(string_error_to_parse_error("type tree node name"))?;
+    let byte_size = file
+        .read_i32(endianess)
+        .context("reading type tree node byte size")?;
+    let index = if version != 3 {
+        file.read_i32(endianess)
+            .context("reading type tree node index")?
+    } else {
+        0
+    };
+    let type_flags = file
+        .read_i32(endianess)
+        .context("reading type tree node type flags")?;
+    let node_version = file
+        .read_i32(endianess)
+        .context("reading type tree node version")?;
+    let meta_flags = if version != 3 {
+        file.read_u32(endianess)
+            .context("reading type tree node meta flags")?
+    } else {
+        0
+    };
+    let children_count = file
+        .read_u32(endianess)
+        .context("reading type tree node children count")?;
+
+    let node = TypeTreeNode {
+        version: node_version,
+        level,
+        type_flags,
+        ty,
+        name,
+        byte_size,
+        index,
+        meta_flags,
+        ref_type_hash: None,
+    };
+
+    Ok((node, children_count))
+}
+
+/// Offsets with their high bit set point into Unity's built-in buffer of common strings rather
+/// than into the type tree's own string buffer
+const COMMON_STRING_FLAG: u32 = 0x8000_0000;
+
+fn resolve_string(string_buffer: &[u8], offset: u32) -> ParseResult<String> {
+    if offset & COMMON_STRING_FLAG != 0 {
+        return match common_string(offset & !COMMON_STRING_FLAG) {
+            Some(string) => Ok(string.to_string()),
+            None => Err(ParseError::expected(
+                "a known common string offset",
+                Vec::from(offset.to_le_bytes()),
+                None,
+            )),
+        };
+    }
+
+    let start = usize::try_from(offset).expect("disunity doesn't support 16-bit platforms");
+    let mut string = match string_buffer.get(start..) {
+        Some(string) => string,
+        None => {
+            return Err(ParseError::expected(
+                "a type tree string offset within the string buffer",
+                Vec::from(offset.to_le_bytes()),
+                None,
+            ))
+        }
+    };
+
+    string
+        .read_null_terminated_string()
+        .map_err(string_error_to_parse_error("type tree string"))
+}
+
+/// Since format version 21 each type tree is followed by the class ids of the types it depends
+/// on
+pub(crate) fn parse_type_dependencies<R: Read>(
+    file: &mut R,
+    endianess: Endianess,
+) -> ParseResult<Vec<i32>> {
+    let count = file
+        .read_u32(endianess)
+        .context("reading type dependencies count")?;
+
+    (0..count)
+        .map(|_| file.read_i32(endianess).context("reading type dependency"))
+        .collect()
+}
+
+pub(crate) fn parse_managed_reference_type<R: BufRead>(
+    file: &mut R,
+) -> ParseResult<ManagedReferenceType> {
+    let class_name = file
+        .read_null_terminated_string()
+        .map_err(string_error_to_parse_error("managed reference class name"))?;
+    let namespace = file
+        .read_null_terminated_string()
+        .map_err(string_error_to_parse_error("managed reference namespace"))?;
+    let assembly_name = file
+        .read_null_terminated_string()
+        .map_err(string_error_to_parse_error(
+            "managed reference assembly name",
+        ))?;
+
+    Ok(ManagedReferenceType {
+        class_name,
+        namespace,
+        assembly_name,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_legacy_type_tree, parse_type_tree_blob, TypeTree};
+    use crate::{Endianess, ParseResult};
+
+    fn legacy_node(ty: &str, name: &str, children_count: u32) -> Vec<u8> {
+        let mut node = Vec::new();
+        node.extend_from_slice(ty.as_bytes());
+        node.push(0);
+        node.extend_from_slice(name.as_bytes());
+        node.push(0);
+        node.extend_from_slice(&4i32.to_le_bytes());
+        node.extend_from_slice(&0i32.to_le_bytes());
+        node.extend_from_slice(&0i32.to_le_bytes());
+        node.extend_from_slice(&1i32.to_le_bytes());
+        node.extend_from_slice(&0x4000u32.to_le_bytes());
+        node.extend_from_slice(&children_count.to_le_bytes());
+        node
+    }
+
+    #[test]
+    fn parse_legacy() {
+        let mut bytes = Vec::new();
+        bytes.extend(legacy_node("Base", "Base", 2));
+        bytes.extend(legacy_node("vector", "m_Values", 1));
+        bytes.extend(legacy_node("Array", "Array", 2));
+        bytes.extend(legacy_node("int", "size", 0));
+        bytes.extend(legacy_node("float", "data", 0));
+        bytes.extend(legacy_node("int", "m_Count", 0));
+        // Anything after the tree must not be read
+        bytes.extend(legacy_node("int", "m_Next", 0));
+
+        let mut reader = &bytes[..];
+        let tree = parse_legacy_type_tree(&mut reader, 9, Endianess::Little).unwrap();
+
+        let nodes = tree
+            .nodes
+            .iter()
+            .map(|node| (node.name.as_str(), node.level))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            nodes,
+            [
+                ("Base", 0),
+                ("m_Values", 1),
+                ("Array", 2),
+                ("size", 3),
+                ("data", 3),
+                ("m_Count", 1)
+            ]
+        );
+        assert_eq!(tree.nodes[0].meta_flags, 0x4000);
+        assert_eq!(reader, &legacy_node("int", "m_Next", 0)[..]);
+    }
+
+    fn blob(type_offset: u32, name_offset: u32, string_buffer: &[u8]) -> Vec<u8> {
+        let mut blob = Vec::new();
+        blob.extend_from_slice(&2u32.to_le_bytes());
+        blob.extend_from_slice(&(string_buffer.len() as u32).to_le_bytes());
+        for (level, type_flags) in [(0u8, 0u8), (1, 1)] {
+            blob.extend_from_slice(&1u16.to_le_bytes());
+            blob.push(level);
+            blob.push(type_flags);
+            blob.extend_from_slice(&type_offset.to_le_bytes());
+            blob.extend_from_slice(&name_offset.to_le_bytes());
+            blob.extend_from_slice(&(-1i32).to_le_bytes());
+            blob.extend_from_slice(&i32::from(level).to_le_bytes());
+            blob.extend_from_slice(&0x4000u32.to_le_bytes());
+            blob.extend_from_slice(&7u64.to_le_bytes());
+        }
+        blob.extend_from_slice(string_buffer);
+        blob
+    }
+
+    fn parse(bytes: &[u8]) -> ParseResult<TypeTree> {
+        parse_type_tree_blob(&mut &bytes[..], 22, Endianess::Little)
+    }
+
+    #[test]
+    fn parse_blob() {
+        let bytes = blob(0, 9, b"MyScript\0m_Values\0");
+        let tree = parse(&bytes).unwrap();
+
+        assert_eq!(tree.nodes.len(), 2);
+        assert_eq!(tree.nodes[0].ty, "MyScript");
+        assert_eq!(tree.nodes[0].name, "m_Values");
+        assert_eq!(tree.nodes[1].level, 1);
+        assert_eq!(tree.nodes[1].type_flags, 1);
+        assert_eq!(tree.nodes[1].byte_size, -1);
+        assert_eq!(tree.nodes[1].meta_flags, 0x4000);
+        assert_eq!(tree.nodes[1].ref_type_hash, Some(7));
+    }
+
+    #[test]
+    fn resolve_common_strings() {
+        let bytes = blob(0x8000_0000 | 981, 0x8000_0000 | 427, b"");
+        let tree = parse(&bytes).unwrap();
+
+        assert_eq!(tree.nodes[0].ty, "vector");
+        assert_eq!(tree.nodes[0].name, "m_Name");
+    }
+
+    #[test]
+    fn reject_truncated_string_buffer() {
+        let mut bytes = blob(0, 9, b"MyScript\0m_Values\0");
+        // A corrupt size runs out of data instead of being allocated
+        bytes[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(parse(&bytes).is_err());
+    }
+
+    #[test]
+    fn reject_offset_outside_of_string_buffer() {
+        let bytes = blob(0, 64, b"MyScript\0");
+        assert!(parse(&bytes).is_err());
+
+        let bytes = blob(0x8000_0000 | 2, 0, b"MyScript\0");
+        assert!(parse(&bytes).is_err());
+    }
+}
@@ -0,0 +1,210 @@
+use crate::error::ParseError;
+use std::{fmt, str::FromStr};
+
+/// Kind of a Unity release, ordered from the least to the most final
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ReleaseType {
+    /// `x`, builds of experimental features
+    Experimental,
+    /// `a`
+    Alpha,
+    /// `b`
+    Beta,
+    /// `rc`, only used by a few Unity 5 releases
+    ReleaseCandidate,
+    /// `f`
+    Final,
+    /// `c`, releases for the Chinese market
+    China,
+    /// `p`, patches on top of a final release
+    Patch,
+}
+
+impl ReleaseType {
+    fn suffix(&self) -> &'static str {
+        match self {
+            ReleaseType::Experimental => "x",
+            ReleaseType::Alpha => "a",
+            ReleaseType::Beta => "b",
+            ReleaseType::ReleaseCandidate => "rc",
+            ReleaseType::Final => "f",
+            ReleaseType::China => "c",
+            ReleaseType::Patch => "p",
+        }
+    }
+}
+
+/// A Unity version such as 2021.3.5f1, as written at the start of serialized files. Versions
+/// are ordered by major, minor, patch, release type, build and then China build
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UnityVersion {
+    pub major: u16,
+    pub minor: u16,
+    pub patch: u16,
+    pub release_type: ReleaseType,
+    pub build: u16,
+    /// Build of the release for the Chinese market made from this one, the `c1` of
+    /// 2021.3.5f1c1
+    pub china_build: Option<u16>,
+}
+
+impl UnityVersion {
+    pub fn new(major: u16, minor: u16, patch: u16, release_type: ReleaseType, build: u16) -> Self {
+        UnityVersion {
+            major,
+            minor,
+            patch,
+            release_type,
+            build,
+            china_build: None,
+        }
+    }
+
+    /// Whether this is `major.minor` or any later version, ignoring the patch and build
+    pub fn at_least(&self, major: u16, minor: u16) -> bool {
+        (self.major, self.minor) >= (major, minor)
+    }
+
+    /// Stripped builds replace the version in every file with 0.0.0, decoding anything that
+    /// depends on the version needs the real one to be found some other way
+    pub fn is_stripped(&self) -> bool {
+        self.major == 0 && self.minor == 0 && self.patch == 0
+    }
+}
+
+/// Splits a string after its leading digits
+fn split_digits(string: &str) -> (&str, &str) {
+    let end = string
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(string.len());
+    string.split_at(end)
+}
+
+impl FromStr for UnityVersion {
+    type Err = ParseError;
+
+    /// Parses versions like "2021.3.5f1", "2021.3.5f1c1", "5.0.0rc2" or "0.0.0". A missing
+    /// release type and build are read as `f0`
+    fn from_str(version: &str) -> Result<Self, Self::Err> {
+        let error = || {
+            ParseError::expected(
+                "a Unity version like 2021.3.5f1",
+                version.as_bytes().to_vec(),
+                None,
+            )
+        };
+        let mut parts = version.splitn(3, '.');
+        let major = parts.next().and_then(|major| major.parse().ok());
+        let minor = parts.next().and_then(|minor| minor.parse().ok());
+        let (major, minor, rest) = match (major, minor, parts.next()) {
+            (Some(major), Some(minor), Some(rest)) => (major, minor, rest),
+            _ => return Err(error()),
+        };
+
+        let (patch, rest) = split_digits(rest);
+        let patch = patch.parse().map_err(|_| error())?;
+        if rest.is_empty() {
+            return Ok(UnityVersion::new(
+                major,
+                minor,
+                patch,
+                ReleaseType::Final,
+                0,
+            ));
+        }
+
+        let (release_type, rest) = [
+            ReleaseType::Experimental,
+            ReleaseType::Alpha,
+            ReleaseType::Beta,
+            ReleaseType::ReleaseCandidate,
+            ReleaseType::Final,
+            ReleaseType::China,
+            ReleaseType::Patch,
+        ]
+        .into_iter()
+        .find_map(|release_type| {
+            rest.strip_prefix(release_type.suffix())
+                .map(|rest| (release_type, rest))
+        })
+        .ok_or_else(error)?;
+        let (build, rest) = split_digits(rest);
+        let build = build.parse().map_err(|_| error())?;
+        let china_build = match rest.strip_prefix(ReleaseType::China.suffix()) {
+            _ if rest.is_empty() => None,
+            Some(china_build) => Some(china_build.parse().map_err(|_| error())?),
+            None => return Err(error()),
+        };
+
+        Ok(UnityVersion {
+            china_build,
+            ..UnityVersion::new(major, minor, patch, release_type, build)
+        })
+    }
+}
+
+impl fmt::Display for UnityVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}.{}.{}{}{}",
+            self.major,
+            self.minor,
+            self.patch,
+            self.release_type.suffix(),
+            self.build
+        )?;
+        if let Some(china_build) = self.china_build {
+            write!(f, "{}{china_build}", ReleaseType::China.suffix())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ReleaseType, UnityVersion};
+
+    #[test]
+    fn parse_and_order() {
+        let version: UnityVersion = "2021.3.5f1".parse().unwrap();
+        assert_eq!(
+            version,
+            UnityVersion::new(2021, 3, 5, ReleaseType::Final, 1)
+        );
+        assert_eq!(version.to_string(), "2021.3.5f1");
+        assert!(version.at_least(2019, 3));
+        assert!(!version.at_least(2022, 1));
+
+        let china: UnityVersion = "2021.3.5f1c1".parse().unwrap();
+        assert_eq!(china.release_type, ReleaseType::Final);
+        assert_eq!(china.china_build, Some(1));
+        assert_eq!(china.to_string(), "2021.3.5f1c1");
+        assert!(china > version);
+        assert!(china < "2021.3.6f1".parse().unwrap());
+        let candidate: UnityVersion = "5.0.0rc2".parse().unwrap();
+        assert_eq!(candidate.release_type, ReleaseType::ReleaseCandidate);
+        assert!(candidate < "5.0.0f1".parse().unwrap());
+        assert!("2019.4.0b3".parse::<UnityVersion>().unwrap() < "2019.4.0f1".parse().unwrap());
+        assert!("2019.4.0p1".parse::<UnityVersion>().unwrap() > "2019.4.0f9".parse().unwrap());
+
+        let stripped: UnityVersion = "0.0.0".parse().unwrap();
+        assert!(stripped.is_stripped());
+        assert!(!version.is_stripped());
+
+        let invalid = [
+            "",
+            "2021",
+            "2021.3",
+            "2021.3.x",
+            "2021.3.5q1",
+            "2021.3.5f",
+            "2021.3.5f1c",
+            "2021.3.5f1x1",
+        ];
+        for invalid in invalid {
+            assert!(invalid.parse::<UnityVersion>().is_err(), "{invalid}");
+        }
+    }
+}
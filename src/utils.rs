@@ -1,7 +1,21 @@
 use crate::Endianess;
-use std::io::{BufRead, BufReader, Error, ErrorKind, Read, Result as IoResult, Seek};
+use std::io::{self, BufRead, Error, ErrorKind, Read, Result as IoResult, Seek};
 
 pub(crate) trait ReadExt: Read {
+    /// Reads and throws away `count` bytes, this works on any reader unlike seeking and doesn't
+    /// discard the buffer of a `BufReader`
+    fn skip(&mut self, count: u64) -> IoResult<()>
+    where
+        Self: Sized,
+    {
+        let skipped = io::copy(&mut self.by_ref().take(count), &mut io::sink())?;
+        if skipped < count {
+            return Err(Error::from(ErrorKind::UnexpectedEof));
+        }
+
+        Ok(())
+    }
+
     fn read_u8(&mut self) -> IoResult<u8> {
         let mut buffer = [0u8; 1];
         self.read_exact(&mut buffer)?;
@@ -10,11 +24,14 @@ pub(crate) trait ReadExt: Read {
 
     fn read_bool(&mut self) -> IoResult<bool> {
         let byte = self.read_u8()?;
-        Ok(match byte {
-            0 => false,
-            1 => true,
-            x => panic!("Expected bool to be 0 or 1 but received {}", x),
-        })
+        match byte {
+            0 => Ok(false),
+            1 => Ok(true),
+            x => Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("expected bool to be 0 or 1 but received {x}"),
+            )),
+        }
     }
 
     fn read_u16(&mut self, endianess: Endianess) -> IoResult<u16> {
@@ -26,6 +43,15 @@ pub(crate) trait ReadExt: Read {
         })
     }
 
+    fn read_i16(&mut self, endianess: Endianess) -> IoResult<i16> {
+        let mut buffer = [0u8; 2];
+        self.read_exact(&mut buffer)?;
+        Ok(match endianess {
+            Endianess::Big => i16::from_be_bytes(buffer),
+            Endianess::Little => i16::from_le_bytes(buffer),
+        })
+    }
+
     fn read_u32(&mut self, endianess: Endianess) -> IoResult<u32> {
         let mut buffer = [0u8; 4];
         self.read_exact(&mut buffer)?;
@@ -53,6 +79,33 @@ pub(crate) trait ReadExt: Read {
         })
     }
 
+    fn read_i64(&mut self, endianess: Endianess) -> IoResult<i64> {
+        let mut buffer = [0u8; 8];
+        self.read_exact(&mut buffer)?;
+        Ok(match endianess {
+            Endianess::Big => i64::from_be_bytes(buffer),
+            Endianess::Little => i64::from_le_bytes(buffer),
+        })
+    }
+
+    fn read_f32(&mut self, endianess: Endianess) -> IoResult<f32> {
+        let mut buffer = [0u8; 4];
+        self.read_exact(&mut buffer)?;
+        Ok(match endianess {
+            Endianess::Big => f32::from_be_bytes(buffer),
+            Endianess::Little => f32::from_le_bytes(buffer),
+        })
+    }
+
+    fn read_f64(&mut self, endianess: Endianess) -> IoResult<f64> {
+        let mut buffer = [0u8; 8];
+        self.read_exact(&mut buffer)?;
+        Ok(match endianess {
+            Endianess::Big => f64::from_be_bytes(buffer),
+            Endianess::Little => f64::from_le_bytes(buffer),
+        })
+    }
+
     fn read_u128(&mut self, endianess: Endianess) -> IoResult<u128> {
         let mut buffer = [0u8; 16];
         self.read_exact(&mut buffer)?;
@@ -93,24 +146,60 @@ pub(crate) trait BufReadExt: BufRead {
 
 impl<T> BufReadExt for T where T: BufRead {}
 
-pub trait SeekExt {
-    fn align_4(&mut self) -> IoResult<()>;
-}
-
-impl<T> SeekExt for BufReader<T>
-where
-    T: Seek,
-{
-    fn align_4(&mut self) -> IoResult<()> {
+pub trait SeekExt: Read + Seek {
+    fn align_4(&mut self) -> IoResult<()>
+    where
+        Self: Sized,
+    {
         let current_position = self.stream_position()?;
         let new_position = (current_position + 3) & !3;
-        let relative = new_position - current_position;
-        if relative > 0 {
-            // This conversion is fine because relative will only ever be less
-            // than 4
-            self.seek_relative(relative as i64)
-        } else {
-            Ok(())
-        }
+        self.skip(new_position - current_position)
+    }
+
+    fn align_16(&mut self) -> IoResult<()>
+    where
+        Self: Sized,
+    {
+        let current_position = self.stream_position()?;
+        let new_position = (current_position + 15) & !15;
+        self.skip(new_position - current_position)
+    }
+}
+
+impl<T> SeekExt for T where T: Read + Seek {}
+
+#[cfg(test)]
+mod tests {
+    use super::{BufReadExt, ReadExt, SeekExt};
+    use std::io::{BufReader, Cursor, Seek};
+
+    #[test]
+    fn align_4_on_any_seekable_reader() {
+        let bytes = [0u8; 12];
+
+        let mut cursor = Cursor::new(&bytes[..]);
+        cursor.skip(5).unwrap();
+        cursor.align_4().unwrap();
+        assert_eq!(cursor.stream_position().unwrap(), 8);
+        cursor.align_4().unwrap();
+        assert_eq!(cursor.stream_position().unwrap(), 8);
+
+        let mut reader = BufReader::new(Cursor::new(Vec::from(bytes)));
+        reader.skip(1).unwrap();
+        reader.align_4().unwrap();
+        assert_eq!(reader.stream_position().unwrap(), 4);
+    }
+
+    #[test]
+    fn skip_past_end() {
+        let mut bytes = &[0u8; 2][..];
+        assert!(bytes.skip(3).is_err());
+    }
+
+    #[test]
+    fn null_terminated_string_from_slice() {
+        let mut bytes = &b"2021.3.5f1\0rest"[..];
+        assert_eq!(bytes.read_null_terminated_string().unwrap(), "2021.3.5f1");
+        assert_eq!(bytes, b"rest");
     }
 }
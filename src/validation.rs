@@ -0,0 +1,246 @@
+use crate::{AssetEntry, SerializedFile, UNITY_4_VERSION};
+use std::fmt;
+
+/// Unity starts every object at a multiple of 8 bytes from the start of the data
+const OBJECT_ALIGNMENT: u64 = 8;
+
+/// An inconsistency between a serialized file's header, its index and its real size. Parsing
+/// succeeds despite these, but they point at a corrupted or tampered file rather than a parser
+/// bug
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Diagnostic {
+    /// The header's file size doesn't match the real size of the file
+    FileSizeMismatch { header: u64, actual: u64 },
+    /// The data starts before the end of the metadata
+    DataOverlapsMetadata { data_offset: u64, metadata_end: u64 },
+    /// The data starts past the end of the file
+    DataOffsetOutOfRange { data_offset: u64, file_size: u64 },
+    /// An object doesn't fit between the start of the data and the end of the file, or the
+    /// start of the metadata for files that store it at the end
+    EntryOutOfBounds {
+        path_id: i64,
+        offset: u64,
+        size: u32,
+        data_end: u64,
+    },
+    /// An object shares bytes with the object before it
+    OverlappingEntries { first: i64, second: i64 },
+    /// An object doesn't start at a multiple of 8 bytes from the start of the data
+    UnalignedEntry { path_id: i64, offset: u64 },
+    /// More than one object uses the same path id
+    DuplicatePathId(i64),
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Diagnostic::FileSizeMismatch { header, actual } => write!(
+                f,
+                "Header says the file is {header} bytes but it is {actual} bytes"
+            ),
+            Diagnostic::DataOverlapsMetadata {
+                data_offset,
+                metadata_end,
+            } => write!(
+                f,
+                "Data starts at {data_offset} before the metadata ends at {metadata_end}"
+            ),
+            Diagnostic::DataOffsetOutOfRange {
+                data_offset,
+                file_size,
+            } => write!(
+                f,
+                "Data starts at {data_offset} past the end of the {file_size} bytes file"
+            ),
+            Diagnostic::EntryOutOfBounds {
+                path_id,
+                offset,
+                size,
+                data_end,
+            } => write!(
+                f,
+                "Object {path_id} at {offset} with {size} bytes goes past the end of the data at {data_end}"
+            ),
+            Diagnostic::OverlappingEntries { first, second } => {
+                write!(f, "Objects {first} and {second} overlap")
+            }
+            Diagnostic::UnalignedEntry { path_id, offset } => {
+                write!(f, "Object {path_id} at {offset} isn't aligned to 8 bytes")
+            }
+            Diagnostic::DuplicatePathId(path_id) => {
+                write!(f, "More than one object has path id {path_id}")
+            }
+        }
+    }
+}
+
+impl SerializedFile {
+    /// Checks the header and index of this file against each other and against `file_size`, the
+    /// real size of the file this was parsed from. An empty list means the file is consistent
+    pub fn validate(&self, file_size: u64) -> Vec<Diagnostic> {
+        let header = &self.header;
+        let mut diagnostics = Vec::new();
+
+        if header.file_size != file_size {
+            diagnostics.push(Diagnostic::FileSizeMismatch {
+                header: header.file_size,
+                actual: file_size,
+            });
+        }
+
+        // Older files keep their metadata after the data instead of before it
+        let (data_end, metadata_before_data) = if header.version >= UNITY_4_VERSION {
+            (file_size, true)
+        } else {
+            (file_size.saturating_sub(u64::from(header.metadata)), false)
+        };
+        let metadata_end = header.metadata_end();
+        if metadata_before_data && header.data_offset < metadata_end {
+            diagnostics.push(Diagnostic::DataOverlapsMetadata {
+                data_offset: header.data_offset,
+                metadata_end,
+            });
+        }
+        if header.data_offset > file_size {
+            diagnostics.push(Diagnostic::DataOffsetOutOfRange {
+                data_offset: header.data_offset,
+                file_size,
+            });
+        }
+
+        for entry in &self.index {
+            let in_bounds = entry.offset >= header.data_offset
+                && entry
+                    .offset
+                    .checked_add(u64::from(entry.size))
+                    .is_some_and(|end| end <= data_end);
+            if !in_bounds {
+                diagnostics.push(Diagnostic::EntryOutOfBounds {
+                    path_id: entry.path_id,
+                    offset: entry.offset,
+                    size: entry.size,
+                    data_end,
+                });
+            }
+
+            if header.version >= UNITY_4_VERSION
+                && entry.offset.wrapping_sub(header.data_offset) % OBJECT_ALIGNMENT != 0
+            {
+                diagnostics.push(Diagnostic::UnalignedEntry {
+                    path_id: entry.path_id,
+                    offset: entry.offset,
+                });
+            }
+        }
+
+        let mut entries: Vec<&AssetEntry> = self.index.iter().collect();
+        entries.sort_by_key(|entry| entry.path_id);
+        for pair in entries.windows(2) {
+            if pair[0].path_id == pair[1].path_id {
+                diagnostics.push(Diagnostic::DuplicatePathId(pair[0].path_id));
+            }
+        }
+
+        // Empty objects can't overlap anything
+        entries.retain(|entry| entry.size > 0);
+        entries.sort_by_key(|entry| entry.offset);
+        // Each entry is checked against the one reaching the furthest so far rather than only its
+        // neighbour, since a large object can cover several smaller ones after it
+        let mut furthest: Option<(u64, i64)> = None;
+        for entry in &entries {
+            let end = entry.offset.saturating_add(u64::from(entry.size));
+            match furthest {
+                Some((furthest_end, path_id)) if furthest_end > entry.offset => {
+                    diagnostics.push(Diagnostic::OverlappingEntries {
+                        first: path_id,
+                        second: entry.path_id,
+                    });
+                    if end > furthest_end {
+                        furthest = Some((end, entry.path_id));
+                    }
+                }
+                _ => furthest = Some((end, entry.path_id)),
+            }
+        }
+
+        diagnostics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Diagnostic;
+    use crate::{tests::version_22_file, AssetEntry, SerializedFile};
+
+    #[test]
+    fn consistent_file() {
+        let bytes = version_22_file();
+        let file = SerializedFile::from_bytes(&bytes).unwrap();
+        assert_eq!(file.validate(bytes.len() as u64), []);
+    }
+
+    #[test]
+    fn report_inconsistencies() {
+        let bytes = version_22_file();
+        let mut file = SerializedFile::from_bytes(&bytes).unwrap();
+        let data_offset = file.header.data_offset;
+        let entry = |path_id, offset, size| AssetEntry {
+            path_id,
+            offset: data_offset + offset,
+            size,
+            type_index: 0,
+        };
+        file.index.push(entry(2, 2, 4));
+        file.index.push(entry(1, 8, 100));
+
+        let file_size = bytes.len() as u64 + 4;
+        assert_eq!(
+            file.validate(file_size),
+            [
+                Diagnostic::FileSizeMismatch {
+                    header: bytes.len() as u64,
+                    actual: file_size,
+                },
+                Diagnostic::UnalignedEntry {
+                    path_id: 2,
+                    offset: data_offset + 2,
+                },
+                Diagnostic::EntryOutOfBounds {
+                    path_id: 1,
+                    offset: data_offset + 8,
+                    size: 100,
+                    data_end: file_size,
+                },
+                Diagnostic::DuplicatePathId(1),
+                Diagnostic::OverlappingEntries {
+                    first: 1,
+                    second: 2,
+                },
+            ]
+        );
+
+        // An object covering several others overlaps each of them
+        let mut file = SerializedFile::from_bytes(&bytes).unwrap();
+        file.index = vec![
+            entry(1, 0, 200),
+            entry(2, 8, 8),
+            entry(3, 24, 8),
+            entry(4, 200, 8),
+        ];
+        let overlapping = |first, second| Diagnostic::OverlappingEntries { first, second };
+        let diagnostics = file.validate(data_offset + 208);
+        let overlaps: Vec<_> = diagnostics
+            .iter()
+            .filter(|diagnostic| matches!(diagnostic, Diagnostic::OverlappingEntries { .. }))
+            .collect();
+        assert_eq!(overlaps, [&overlapping(1, 2), &overlapping(1, 3)]);
+
+        file.header.data_offset = 16;
+        assert!(file
+            .validate(bytes.len() as u64)
+            .contains(&Diagnostic::DataOverlapsMetadata {
+                data_offset: 16,
+                metadata_end: data_offset,
+            }));
+    }
+}
@@ -0,0 +1,357 @@
+use crate::{
+    error::{ParseError, ParseResult, ParserContext},
+    type_tree::{TypeTree, TypeTreeNode},
+    utils::{ReadExt, SeekExt},
+    Endianess,
+};
+use std::io::{Read, Seek};
+
+/// Meta flag of type tree nodes whose data is followed by padding up to a multiple of 4 bytes
+const ALIGN_BYTES_FLAG: u32 = 0x4000;
+
+/// Type flag of type tree nodes that are arrays
+const ARRAY_FLAG: i32 = 0x1;
+
+/// An object read by following its type tree, without knowing its class ahead of time
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    /// Any signed integer, from `SInt8` up to `SInt64`
+    Int(i64),
+    /// Any unsigned integer, from `UInt8` and `char` up to `UInt64`
+    UInt(u64),
+    Float(f32),
+    Double(f64),
+    String(String),
+    /// Contents of `TypelessData`, or of a `string` that isn't valid UTF-8 like the script of a
+    /// `TextAsset` holding binary data
+    Bytes(Vec<u8>),
+    Array(Vec<Value>),
+    /// Entries of a `map` in the order they were stored
+    Map(Vec<(Value, Value)>),
+    /// Fields of a class in the order they were stored, paired with their names
+    Struct {
+        ty: String,
+        fields: Vec<(String, Value)>,
+    },
+}
+
+impl Value {
+    /// Looks up a field by name when this value is a struct
+    pub fn field(&self, name: &str) -> Option<&Value> {
+        match self {
+            Value::Struct { fields, .. } => fields
+                .iter()
+                .find(|(field_name, _)| field_name == name)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+}
+
+impl TypeTree {
+    /// Reads one object laid out as described by this type tree. Alignment is relative to the
+    /// start of `reader`, so it should be positioned at the start of the object's data
+    pub fn read_value<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        endianess: Endianess,
+    ) -> ParseResult<Value> {
+        if self.nodes.is_empty() {
+            return Err(ParseError::expected(
+                "a type tree with at least one node",
+                Vec::new(),
+                None,
+            ));
+        }
+
+        read_node(&self.nodes, reader, endianess)
+    }
+}
+
+/// Returns the node at `index` followed by all of its descendants
+fn subtree(nodes: &[TypeTreeNode], index: usize) -> ParseResult<&[TypeTreeNode]> {
+    let level = match nodes.get(index) {
+        Some(node) => node.level,
+        None => {
+            return Err(ParseError::expected(
+                "a type tree node with the children its type needs",
+                Vec::new(),
+                None,
+            ))
+        }
+    };
+    let end = nodes[index + 1..]
+        .iter()
+        .position(|node| node.level <= level)
+        .map_or(nodes.len(), |position| index + 1 + position);
+
+    Ok(&nodes[index..end])
+}
+
+fn read_size<R: Read>(reader: &mut R, endianess: Endianess) -> ParseResult<usize> {
+    let size = reader
+        .read_i32(endianess)
+        .context("reading object array size")?;
+    usize::try_from(size).map_err(|_| {
+        ParseError::expected(
+            "a non-negative object array size",
+            size.to_le_bytes().to_vec(),
+            None,
+        )
+    })
+}
+
+fn read_sized_bytes<R: Read>(reader: &mut R, endianess: Endianess) -> ParseResult<Vec<u8>> {
+    let size = read_size(reader, endianess)?;
+    // Reading through `take` avoids allocating a corrupt size up front
+    let mut bytes = Vec::new();
+    reader
+        .take(size as u64)
+        .read_to_end(&mut bytes)
+        .context("reading object bytes")?;
+    if bytes.len() != size {
+        return Err(ParseError::expected(
+            format!("{} bytes of object data", size),
+            bytes,
+            None,
+        ));
+    }
+
+    Ok(bytes)
+}
+
+fn has_align_flag(node: Option<&TypeTreeNode>) -> bool {
+    node.is_some_and(|node| node.meta_flags & ALIGN_BYTES_FLAG != 0)
+}
+
+/// Reads the value of `nodes[0]`, where `nodes` is the subtree of that node
+fn read_node<R: Read + Seek>(
+    nodes: &[TypeTreeNode],
+    reader: &mut R,
+    endianess: Endianess,
+) -> ParseResult<Value> {
+    let node = &nodes[0];
+    let children = &nodes[1..];
+    let mut align = has_align_flag(Some(node));
+
+    let value = match node.ty.as_str() {
+        "bool" => Value::Bool(reader.read_u8().context("reading bool")? != 0),
+        "SInt8" => Value::Int(reader.read_u8().context("reading SInt8")? as i8 as i64),
+        "UInt8" | "char" => Value::UInt(reader.read_u8().context("reading UInt8")? as u64),
+        "SInt16" | "short" => {
+            Value::Int(reader.read_i16(endianess).context("reading SInt16")? as i64)
+        }
+        "UInt16" | "unsigned short" => {
+            Value::UInt(reader.read_u16(endianess).context("reading UInt16")? as u64)
+        }
+        "SInt32" | "int" => {
+            Value::Int(reader.read_i32(endianess).context("reading SInt32")? as i64)
+        }
+        "UInt32" | "unsigned int" | "Type*" => {
+            Value::UInt(reader.read_u32(endianess).context("reading UInt32")? as u64)
+        }
+        "SInt64" | "long long" => Value::Int(reader.read_i64(endianess).context("reading SInt64")?),
+        "UInt64" | "unsigned long long" | "FileSize" => {
+            Value::UInt(reader.read_u64(endianess).context("reading UInt64")?)
+        }
+        "float" => Value::Float(reader.read_f32(endianess).context("reading float")?),
+        "double" => Value::Double(reader.read_f64(endianess).context("reading double")?),
+        "string" => {
+            // Strings have an `Array` child of chars, which carries the alignment
+            align |= has_align_flag(children.first());
+            match String::from_utf8(read_sized_bytes(reader, endianess)?) {
+                Ok(string) => Value::String(string),
+                Err(error) => Value::Bytes(error.into_bytes()),
+            }
+        }
+        "TypelessData" => Value::Bytes(read_sized_bytes(reader, endianess)?),
+        "map" => {
+            // map > Array > (size, data: pair > (first, second))
+            let array = subtree(children, 0)?;
+            align |= has_align_flag(array.first());
+            let pair = subtree(array, 2)?;
+            let first = subtree(pair, 1)?;
+            let second = subtree(pair, 1 + first.len())?;
+
+            let size = read_size(reader, endianess)?;
+            let mut entries = Vec::new();
+            for _ in 0..size {
+                let key = read_node(first, reader, endianess)?;
+                let value = read_node(second, reader, endianess)?;
+                entries.push((key, value));
+            }
+            Value::Map(entries)
+        }
+        _ if node.type_flags & ARRAY_FLAG != 0 => {
+            // Array > (size, data)
+            let element = subtree(children, 1)?;
+            read_array(element, reader, endianess)?
+        }
+        _ if children.first().is_some_and(|child| child.ty == "Array") => {
+            // vector > Array > (size, data)
+            let array = subtree(children, 0)?;
+            align |= has_align_flag(array.first());
+            let element = subtree(array, 2)?;
+            read_array(element, reader, endianess)?
+        }
+        _ if children.is_empty() && node.byte_size > 0 => {
+            return Err(ParseError::expected(
+                format!("a known primitive type instead of {}", node.ty),
+                Vec::new(),
+                None,
+            ));
+        }
+        _ => {
+            let mut fields = Vec::new();
+            let mut index = 0;
+            while index < children.len() {
+                let field = subtree(children, index)?;
+                let value = read_node(field, reader, endianess)?;
+                fields.push((field[0].name.clone(), value));
+                index += field.len();
+            }
+            Value::Struct {
+                ty: node.ty.clone(),
+                fields,
+            }
+        }
+    };
+
+    if align {
+        reader.align_4().context("aligning object data")?;
+    }
+
+    Ok(value)
+}
+
+fn read_array<R: Read + Seek>(
+    element: &[TypeTreeNode],
+    reader: &mut R,
+    endianess: Endianess,
+) -> ParseResult<Value> {
+    let size = read_size(reader, endianess)?;
+    // Elements are pushed one at a time so that a corrupt size runs out of data instead of
+    // allocating everything up front
+    let mut elements = Vec::new();
+    for _ in 0..size {
+        elements.push(read_node(element, reader, endianess)?);
+    }
+
+    Ok(Value::Array(elements))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Value;
+    use crate::{
+        type_tree::{TypeTree, TypeTreeNode},
+        Endianess,
+    };
+    use std::io::Cursor;
+
+    fn node(level: u8, ty: &str, name: &str, byte_size: i32, meta_flags: u32) -> TypeTreeNode {
+        TypeTreeNode {
+            version: 1,
+            level,
+            type_flags: (ty == "Array") as i32,
+            ty: ty.to_owned(),
+            name: name.to_owned(),
+            byte_size,
+            index: 0,
+            meta_flags,
+            ref_type_hash: None,
+        }
+    }
+
+    #[test]
+    fn read_object() {
+        let tree = TypeTree {
+            nodes: vec![
+                node(0, "Example", "Base", -1, 0),
+                node(1, "string", "m_Name", -1, 0),
+                node(2, "Array", "Array", -1, 0x4000),
+                node(3, "int", "size", 4, 0),
+                node(3, "char", "data", 1, 0),
+                node(1, "bool", "m_Enabled", 1, 0x4000),
+                node(1, "vector", "m_Values", -1, 0),
+                node(2, "Array", "Array", -1, 0),
+                node(3, "int", "size", 4, 0),
+                node(3, "float", "data", 4, 0),
+                node(1, "map", "m_Lookup", -1, 0),
+                node(2, "Array", "Array", -1, 0),
+                node(3, "int", "size", 4, 0),
+                node(3, "pair", "data", -1, 0),
+                node(4, "SInt64", "first", 8, 0),
+                node(4, "Vector2f", "second", 8, 0),
+                node(5, "float", "x", 4, 0),
+                node(5, "float", "y", 4, 0),
+                node(1, "TypelessData", "m_Data", -1, 0),
+                node(2, "int", "size", 4, 0),
+                node(2, "UInt8", "data", 1, 0),
+            ],
+        };
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&5i32.to_le_bytes());
+        data.extend_from_slice(b"Hello\0\0\0");
+        data.extend_from_slice(&[1, 0, 0, 0]);
+        data.extend_from_slice(&1i32.to_le_bytes());
+        data.extend_from_slice(&1.5f32.to_le_bytes());
+        data.extend_from_slice(&1i32.to_le_bytes());
+        data.extend_from_slice(&(-2i64).to_le_bytes());
+        data.extend_from_slice(&3f32.to_le_bytes());
+        data.extend_from_slice(&4f32.to_le_bytes());
+        data.extend_from_slice(&2i32.to_le_bytes());
+        data.extend_from_slice(&[0xAB, 0xCD]);
+
+        let mut reader = Cursor::new(&data[..]);
+        let value = tree.read_value(&mut reader, Endianess::Little).unwrap();
+        assert_eq!(reader.position(), data.len() as u64);
+
+        let vector = |x, y| Value::Struct {
+            ty: "Vector2f".to_owned(),
+            fields: vec![
+                ("x".to_owned(), Value::Float(x)),
+                ("y".to_owned(), Value::Float(y)),
+            ],
+        };
+        assert_eq!(
+            value,
+            Value::Struct {
+                ty: "Example".to_owned(),
+                fields: vec![
+                    ("m_Name".to_owned(), Value::String("Hello".to_owned())),
+                    ("m_Enabled".to_owned(), Value::Bool(true)),
+                    ("m_Values".to_owned(), Value::Array(vec![Value::Float(1.5)])),
+                    (
+                        "m_Lookup".to_owned(),
+                        Value::Map(vec![(Value::Int(-2), vector(3.0, 4.0))])
+                    ),
+                    ("m_Data".to_owned(), Value::Bytes(vec![0xAB, 0xCD])),
+                ],
+            }
+        );
+        assert_eq!(
+            value.field("m_Name"),
+            Some(&Value::String("Hello".to_owned()))
+        );
+    }
+
+    #[test]
+    fn reject_negative_size() {
+        let tree = TypeTree {
+            nodes: vec![
+                node(0, "vector", "Base", -1, 0),
+                node(1, "Array", "Array", -1, 0),
+                node(2, "int", "size", 4, 0),
+                node(2, "int", "data", 4, 0),
+            ],
+        };
+
+        let data = (-1i32).to_le_bytes();
+        assert!(tree
+            .read_value(&mut Cursor::new(&data[..]), Endianess::Little)
+            .is_err());
+    }
+}